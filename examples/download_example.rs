@@ -9,7 +9,6 @@
 use std::fs::File;
 use std::time::Instant;
 use ureq::AgentBuilder;
-use url;
 
 struct RequestData {
     endpoint: url::Url,
@@ -17,7 +16,7 @@ struct RequestData {
     secret: String,
     bucket: String,
     key: String,
-    region: String,
+    region: s3v4::Region,
 }
 
 fn main() -> Result<(), String> {
@@ -29,8 +28,8 @@ fn main() -> Result<(), String> {
     let access = std::env::var("S3_ACCESS").map_err(|err| err.to_string())?;
     let secret = std::env::var("S3_SECRET").map_err(|err| err.to_string())?;
     let region = match std::env::args().nth(5) {
-        Some(r) => r,
-        _ => "us-east-1".to_string(),
+        Some(r) => r.parse::<s3v4::Region>().map_err(|err| err.to_string())?,
+        _ => s3v4::Region::UsEast1,
     };
     let start = Instant::now();
     let rd = RequestData {
@@ -62,14 +61,14 @@ fn download_object(req_data: &RequestData, filename: &str) -> Result<u64, String
     );
 
     let url = url::Url::parse(&uri).map_err(|err| err.to_string())?;
-    let method = "GET";
+    let method = &s3v4::Method::Get;
     let signature = s3v4::signature(
         &url,
         method,
         &req_data.access,
         &req_data.secret,
         &req_data.region,
-        &"s3",
+        "s3",
         "UNSIGNED-PAYLOAD",
     )
     .map_err(|err| format!("{:?}", err))?;
@@ -31,19 +31,26 @@
 //! ```shell
 //! cargo run --example presign https://play.min.io/bucket/key $S3_ACCESS $S3_SECRET GET 10000 "us-east-1" "2022-06-14T00:00:00Z"
 //! ```
-use url;
 fn main() -> Result<(), String> {
     let url =
         url::Url::parse(&std::env::args().nth(1).expect("missing url")).expect("malformed URL");
     let access = std::env::args().nth(2).expect("missing access");
     let secret = std::env::args().nth(3).expect("missing secret");
-    let method = std::env::args().nth(4).expect("missing method");
+    let method = std::env::args()
+        .nth(4)
+        .expect("missing method")
+        .parse::<s3v4::Method>()
+        .expect("invalid method");
     let expiration = std::env::args()
         .nth(5)
         .expect("missing expiration (seconds)")
         .parse::<u64>()
         .expect("wrong expiration format");
-    let region = std::env::args().nth(6).expect("missing region");
+    let region = std::env::args()
+        .nth(6)
+        .expect("missing region")
+        .parse::<s3v4::Region>()
+        .expect("invalid region");
     let service = std::env::args().nth(7).expect("missing service");
     let date_time: chrono::DateTime<chrono::Utc> = match std::env::args().nth(8) {
         Some(d) => chrono::DateTime::parse_from_rfc3339(&d)
@@ -58,7 +65,7 @@ fn main() -> Result<(), String> {
         expiration,
         &url,
         &method,
-        &payload_hash,
+        payload_hash,
         &region,
         &date_time,
         &service,
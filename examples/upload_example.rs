@@ -18,7 +18,6 @@
 use std::fs::File;
 use std::time::Instant;
 use ureq::AgentBuilder;
-use url;
 type HeaderMap = std::collections::HashMap<String, String>;
 
 struct RequestData {
@@ -27,7 +26,7 @@ struct RequestData {
     secret: String,
     bucket: String,
     key: String,
-    region: String,
+    region: s3v4::Region,
 }
 fn main() -> Result<(), String> {
     let file_name = std::env::args().nth(1).expect("missing file name");
@@ -38,8 +37,8 @@ fn main() -> Result<(), String> {
     let access = std::env::var("S3_ACCESS").map_err(|err| err.to_string())?;
     let secret = std::env::var("S3_SECRET").map_err(|err| err.to_string())?;
     let region = match std::env::args().nth(5) {
-        Some(r) => r,
-        _ => "us-east-1".to_string(),
+        Some(r) => r.parse::<s3v4::Region>().map_err(|err| err.to_string())?,
+        _ => s3v4::Region::UsEast1,
     };
     let headers = match std::env::args().nth(6) {
         Some(h) => parse_headers(&h),
@@ -74,9 +73,9 @@ fn main() -> Result<(), String> {
 /// Headers are formatted as "key:value" pairs, separated by the `;` character.
 fn parse_headers(h: &str) -> HeaderMap {
     let mut headers = HeaderMap::new();
-    h.split(';').into_iter().for_each(|s| {
+    h.split(';').for_each(|s| {
         let (k, v) = (
-            s.split(':').nth(0).expect("Missing header").trim(),
+            s.split(':').next().expect("Missing header").trim(),
             s.split(':').nth(1).expect("Missing header value").trim(),
         );
         if !k.is_empty() && !v.is_empty() {
@@ -102,14 +101,14 @@ fn upload_object(
     );
 
     let url = url::Url::parse(&uri).map_err(|err| err.to_string())?;
-    let method = "PUT";
+    let method = &s3v4::Method::Put;
     let signature = s3v4::signature(
         &url,
         method,
         &req_data.access,
         &req_data.secret,
         &req_data.region,
-        &"s3",
+        "s3",
         "UNSIGNED-PAYLOAD",
     )
     .map_err(|err| format!("{:?}", err))?;
@@ -0,0 +1,269 @@
+//! S3 access point ARNs.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/userguide/using-access-points.html)
+
+use crate::errors::*;
+use crate::{Region, SHORT_DATE_FMT};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+/// Algorithm used to sign requests to Multi-Region Access Points.
+pub const SIGV4A_ALGORITHM: &str = "AWS4-ECDSA-P256-SHA256";
+/// Name of the signed header containing the SigV4A region set.
+pub const REGION_SET_HEADER: &str = "x-amz-region-set";
+/// Service name of S3 Object Lambda, used in ARNs and signature scopes.
+pub const OBJECT_LAMBDA_SERVICE: &str = "s3-object-lambda";
+
+// -----------------------------------------------------------------------------
+/// Parsed S3 access point ARN.
+///
+/// Single region access points (`arn:aws:s3:region:account:accesspoint/name`),
+/// Multi-Region Access Points (`arn:aws:s3::account:accesspoint/alias.mrap`)
+/// and Object Lambda access points
+/// (`arn:aws:s3-object-lambda:region:account:accesspoint/name`) are parsed.
+///
+/// Requests to Multi-Region Access Points must be signed with SigV4A
+/// ([SIGV4A_ALGORITHM]), which this crate does not implement: presigning them
+/// fails, while [AccessPointArn::host], [AccessPointArn::signing_region] and
+/// [AccessPointArn::sigv4a_scope] provide the parameters of a SigV4A signer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AccessPointArn {
+    /// Partition: `aws`, `aws-cn` or `aws-us-gov`.
+    pub partition: String,
+    /// Service: `s3`, or [OBJECT_LAMBDA_SERVICE] for Object Lambda access points.
+    pub service: String,
+    /// Region of the access point, `None` for Multi-Region Access Points.
+    pub region: Option<Region>,
+    /// Account ID of the access point owner.
+    pub account: String,
+    /// Access point name, or alias for Multi-Region Access Points.
+    pub name: String,
+}
+
+impl AccessPointArn {
+    /// Return `true` if this is the ARN of a Multi-Region Access Point, which
+    /// requires requests to be signed with SigV4A.
+    pub fn is_multi_region(&self) -> bool {
+        self.region.is_none()
+    }
+
+    /// Return `true` if this is the ARN of an Object Lambda access point.
+    pub fn is_object_lambda(&self) -> bool {
+        self.service == OBJECT_LAMBDA_SERVICE
+    }
+
+    /// Return the service to use in the signature scope: `s3`, or
+    /// `s3-object-lambda` for Object Lambda access points.
+    pub fn signing_service(&self) -> &str {
+        &self.service
+    }
+
+    /// Return the region to use in the signature scope: the access point region
+    /// or `*` (SigV4A region set matching all regions) for Multi-Region Access Points.
+    pub fn signing_region(&self) -> &str {
+        match &self.region {
+            Some(region) => region.as_str(),
+            None => "*",
+        }
+    }
+
+    /// Return the SigV4A credential scope of requests to a Multi-Region Access
+    /// Point signed at `date_time`, e.g. `20150830/s3/aws4_request`, or `None`
+    /// for single region access points.
+    ///
+    /// Unlike SigV4 scopes, the scope does not contain the region: the region
+    /// set returned by [AccessPointArn::signing_region] is sent and signed in
+    /// the [REGION_SET_HEADER] header instead.
+    pub fn sigv4a_scope(&self, date_time: &DateTime<Utc>) -> Option<String> {
+        if !self.is_multi_region() {
+            return None;
+        }
+        Some(format!(
+            "{}/{}/aws4_request",
+            date_time.format(SHORT_DATE_FMT),
+            self.signing_service()
+        ))
+    }
+
+    /// Return the host name of the access point endpoint.
+    pub fn host(&self) -> String {
+        match &self.region {
+            Some(region) if self.is_object_lambda() => format!(
+                "{}-{}.{}.{}.{}",
+                self.name,
+                self.account,
+                OBJECT_LAMBDA_SERVICE,
+                region,
+                dns_suffix(&self.partition)
+            ),
+            Some(region) => format!(
+                "{}-{}.s3-accesspoint.{}.{}",
+                self.name,
+                self.account,
+                region,
+                dns_suffix(&self.partition)
+            ),
+            None => format!(
+                "{}.accesspoint.s3-global.{}",
+                self.name,
+                dns_suffix(&self.partition)
+            ),
+        }
+    }
+}
+
+/// Return the DNS suffix of the given partition.
+fn dns_suffix(partition: &str) -> &'static str {
+    match partition {
+        "aws-cn" => "amazonaws.com.cn",
+        _ => "amazonaws.com",
+    }
+}
+
+impl FromStr for AccessPointArn {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.splitn(6, ':').collect::<Vec<_>>();
+        if parts.len() != 6 || parts[0] != "arn" {
+            bail!("Invalid ARN: '{}'", s);
+        }
+        let (partition, service, region, account, resource) =
+            (parts[1], parts[2], parts[3], parts[4], parts[5]);
+        if !matches!(partition, "aws" | "aws-cn" | "aws-us-gov") {
+            bail!("Invalid ARN '{}': unknown partition '{}'", s, partition);
+        }
+        if service != "s3" && service != OBJECT_LAMBDA_SERVICE {
+            bail!("Invalid ARN '{}': not an S3 ARN", s);
+        }
+        if account.len() != 12 || !account.chars().all(|c| c.is_ascii_digit()) {
+            bail!("Invalid ARN '{}': invalid account ID '{}'", s, account);
+        }
+        let name = match resource
+            .strip_prefix("accesspoint/")
+            .or_else(|| resource.strip_prefix("accesspoint:"))
+        {
+            Some(name) => name,
+            None => bail!("Invalid ARN '{}': not an access point", s),
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        {
+            bail!("Invalid ARN '{}': invalid access point name '{}'", s, name);
+        }
+        let region = if region.is_empty() {
+            if !name.ends_with(".mrap") || service == OBJECT_LAMBDA_SERVICE {
+                bail!(
+                    "Invalid ARN '{}': missing region for single region access point",
+                    s
+                );
+            }
+            None
+        } else {
+            Some(
+                region
+                    .parse::<Region>()
+                    .chain_err(|| format!("Invalid ARN '{}'", s))?,
+            )
+        };
+        Ok(AccessPointArn {
+            partition: partition.to_string(),
+            service: service.to_string(),
+            region,
+            account: account.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Parse `bucket` as an access point ARN if it starts with `arn:`.
+pub(crate) fn parse_bucket_arn(bucket: &str) -> Result<Option<AccessPointArn>> {
+    if bucket.starts_with("arn:") {
+        Ok(Some(bucket.parse()?))
+    } else {
+        Ok(None)
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_access_point_arn() -> Result<()> {
+        let arn: AccessPointArn = "arn:aws:s3:us-west-2:123456789012:accesspoint/my-ap".parse()?;
+        assert_eq!(Some(Region::UsWest2), arn.region);
+        assert!(!arn.is_multi_region());
+        assert_eq!("us-west-2", arn.signing_region());
+        assert_eq!(
+            "my-ap-123456789012.s3-accesspoint.us-west-2.amazonaws.com",
+            arn.host()
+        );
+        let arn: AccessPointArn =
+            "arn:aws-cn:s3:cn-north-1:123456789012:accesspoint:my-ap".parse()?;
+        assert_eq!(
+            "my-ap-123456789012.s3-accesspoint.cn-north-1.amazonaws.com.cn",
+            arn.host()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_multi_region_access_point_arn() -> Result<()> {
+        let arn: AccessPointArn =
+            "arn:aws:s3::123456789012:accesspoint/mfzwi23gnjvgw.mrap".parse()?;
+        assert!(arn.is_multi_region());
+        assert_eq!(None, arn.region);
+        assert_eq!("mfzwi23gnjvgw.mrap", arn.name);
+        assert_eq!("*", arn.signing_region());
+        assert_eq!(
+            "mfzwi23gnjvgw.mrap.accesspoint.s3-global.amazonaws.com",
+            arn.host()
+        );
+        let date_time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        assert_eq!(
+            Some("20150830/s3/aws4_request".to_string()),
+            arn.sigv4a_scope(&date_time)
+        );
+        let arn: AccessPointArn = "arn:aws:s3:us-west-2:123456789012:accesspoint/my-ap".parse()?;
+        assert_eq!(None, arn.sigv4a_scope(&date_time));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_object_lambda_arn() -> Result<()> {
+        let arn: AccessPointArn =
+            "arn:aws:s3-object-lambda:us-west-2:123456789012:accesspoint/my-olap".parse()?;
+        assert!(arn.is_object_lambda() && !arn.is_multi_region());
+        assert_eq!("s3-object-lambda", arn.signing_service());
+        assert_eq!("us-west-2", arn.signing_region());
+        assert_eq!(
+            "my-olap-123456789012.s3-object-lambda.us-west-2.amazonaws.com",
+            arn.host()
+        );
+        let arn: AccessPointArn = "arn:aws:s3:us-west-2:123456789012:accesspoint/my-ap".parse()?;
+        assert!(!arn.is_object_lambda());
+        assert_eq!("s3", arn.signing_service());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_arn() {
+        for arn in [
+            "arn:aws:s3:us-west-2:123456789012",
+            "arn:aws:s3:us-west-2:123456789012:bucket/my-bucket",
+            "arn:aws:sqs:us-west-2:123456789012:accesspoint/my-ap",
+            "arn:aws:s3:us-west-2:1234:accesspoint/my-ap",
+            "arn:aws:s3::123456789012:accesspoint/my-ap",
+            "arn:aws:s3:us-west2:123456789012:accesspoint/my-ap",
+            "arn:aws:s3-object-lambda::123456789012:accesspoint/my-olap.mrap",
+        ] {
+            assert!(arn.parse::<AccessPointArn>().is_err(), "{}", arn);
+        }
+    }
+}
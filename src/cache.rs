@@ -0,0 +1,269 @@
+//! Caching of pre-signed URLs.
+
+use crate::errors::*;
+use crate::presign::presign_object;
+use crate::{Method, PresignOptions};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use url::Url;
+
+/// Maximum expiration of pre-signed URLs accepted by S3: seven days.
+const MAX_EXPIRATION: u64 = 7 * 24 * 3600;
+
+// -----------------------------------------------------------------------------
+/// Pre-signer reusing the URLs generated for the same request until shortly
+/// before they expire.
+///
+/// URLs are cached per method, bucket, key and options and regenerated on
+/// demand when less than `margin` seconds are left before their expiration.
+/// The pre-signer can be shared between threads, e.g. through an `Arc`.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// let endpoint = url::Url::parse("https://play.min.io").unwrap();
+/// // URLs valid for one hour, reused for at most 55 minutes
+/// let presigner = s3v4::CachingPresigner::new("access", "secret", endpoint, 3600, 300)?;
+/// let options = s3v4::PresignOptions::default();
+/// let first = presigner.presign_get_object("bucket", "key", &options)?;
+/// let second = presigner.presign_get_object("bucket", "key", &options)?;
+/// assert_eq!(first, second);
+/// # Ok(())
+/// # }
+/// ```
+pub struct CachingPresigner {
+    access: String,
+    secret: String,
+    endpoint: Url,
+    expiration: u64,
+    margin: u64,
+    cache: Mutex<Cache>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: Method,
+    bucket: String,
+    key: String,
+    options: PresignOptions,
+}
+
+struct CachedUrl {
+    url: String,
+    expires: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Cache {
+    urls: HashMap<CacheKey, CachedUrl>,
+    /// Number of entries triggering the removal of expired URLs.
+    purge_at: usize,
+}
+
+impl CachingPresigner {
+    /// Create a pre-signer generating URLs valid for `expiration` seconds and
+    /// reusing them until `margin` seconds before they expire; `expiration`
+    /// must not exceed seven days.
+    pub fn new(
+        access: &str,
+        secret: &str,
+        endpoint: Url,
+        expiration: u64,
+        margin: u64,
+    ) -> Result<Self> {
+        if expiration > MAX_EXPIRATION {
+            bail!(
+                "Expiration ({}s) must not exceed seven days ({}s)",
+                expiration,
+                MAX_EXPIRATION
+            );
+        }
+        if margin >= expiration {
+            bail!(
+                "Margin ({}s) must be less than the expiration ({}s)",
+                margin,
+                expiration
+            );
+        }
+        Ok(CachingPresigner {
+            access: access.to_string(),
+            secret: secret.to_string(),
+            endpoint,
+            expiration,
+            margin,
+            cache: Mutex::new(Cache::default()),
+        })
+    }
+
+    /// Return a pre-signed URL for the specified object, generating it if not
+    /// cached or close to expiration.
+    ///
+    /// URLs are signed at the time they are generated: an error is returned if
+    /// `options.date_time` is set, since URLs signed at a fixed time would be
+    /// expired when renewed.
+    pub fn presign(
+        &self,
+        method: &Method,
+        bucket: &str,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String> {
+        if options.date_time.is_some() {
+            bail!("Cached URLs cannot be signed at a fixed date and time");
+        }
+        let cache_key = CacheKey {
+            method: method.clone(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            options: options.clone(),
+        };
+        let now = Utc::now();
+        let margin = Duration::seconds(self.margin as i64);
+        if let Some(cached) = self.lock().urls.get(&cache_key) {
+            if cached.expires - margin > now {
+                return Ok(cached.url.clone());
+            }
+        }
+        // sign outside the lock, concurrent misses for the same URL may both sign
+        let signing_options = PresignOptions {
+            date_time: Some(now),
+            ..options.clone()
+        };
+        let url = presign_object(
+            method,
+            &self.access,
+            &self.secret,
+            &self.endpoint,
+            bucket,
+            key,
+            self.expiration,
+            &signing_options,
+        )?;
+        let expires = now + Duration::seconds(self.expiration as i64);
+        let mut cache = self.lock();
+        if cache.urls.len() >= cache.purge_at {
+            cache.urls.retain(|_, cached| cached.expires - margin > now);
+            cache.purge_at = (2 * cache.urls.len()).max(64);
+        }
+        cache.urls.insert(
+            cache_key,
+            CachedUrl {
+                url: url.clone(),
+                expires,
+            },
+        );
+        Ok(url)
+    }
+
+    /// Return a pre-signed URL to download an object.
+    pub fn presign_get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String> {
+        self.presign(&Method::Get, bucket, key, options)
+    }
+
+    /// Return a pre-signed URL to upload an object.
+    pub fn presign_put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String> {
+        self.presign(&Method::Put, bucket, key, options)
+    }
+
+    /// Return a pre-signed URL to retrieve object metadata.
+    pub fn presign_head_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &PresignOptions,
+    ) -> Result<String> {
+        self.presign(&Method::Head, bucket, key, options)
+    }
+
+    /// Remove all the cached URLs, e.g. after the credentials were revoked.
+    pub fn clear(&self) {
+        self.lock().urls.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // the cache is always left in a consistent state
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caching_presigner() -> Result<()> {
+        let endpoint = Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?;
+        let presigner = CachingPresigner::new("access", "secret", endpoint, 3600, 60)?;
+        let options = PresignOptions::default();
+        let url = presigner.presign_get_object("bucket", "key", &options)?;
+        assert_eq!(
+            url,
+            presigner.presign_get_object("bucket", "key", &options)?
+        );
+        assert_ne!(
+            url,
+            presigner.presign_put_object("bucket", "key", &options)?
+        );
+        assert_ne!(
+            url,
+            presigner.presign_get_object("bucket", "key2", &options)?
+        );
+        // URLs close to expiration are regenerated
+        let cache_key = CacheKey {
+            method: Method::Get,
+            bucket: "bucket".to_string(),
+            key: "key".to_string(),
+            options: options.clone(),
+        };
+        presigner.lock().urls.insert(
+            cache_key,
+            CachedUrl {
+                url: "expiring".to_string(),
+                expires: Utc::now() + Duration::seconds(30),
+            },
+        );
+        assert_ne!(
+            "expiring",
+            presigner.presign_get_object("bucket", "key", &options)?
+        );
+        presigner.clear();
+        assert!(presigner.lock().urls.is_empty());
+        let fixed = PresignOptions {
+            date_time: Some(Utc::now()),
+            ..Default::default()
+        };
+        assert!(presigner
+            .presign_get_object("bucket", "key", &fixed)
+            .is_err());
+        assert!(presigner.lock().urls.is_empty());
+        assert!(CachingPresigner::new(
+            "access",
+            "secret",
+            Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?,
+            60,
+            60
+        )
+        .is_err());
+        assert!(CachingPresigner::new(
+            "access",
+            "secret",
+            Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?,
+            u64::MAX,
+            u64::MAX - 1
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,582 @@
+//! Async S3 client built on the `reqwest` crate, available with the `async` feature.
+//!
+//! The async client signs requests like [Client](super::Client) and shares its
+//! types: in particular a [MultipartUploadState] saved by either client can be
+//! resumed by the other.
+//!
+//! ```no_run
+//! # async fn run() -> s3v4::Result<()> {
+//! use s3v4::client::AsyncClient;
+//! let endpoint = url::Url::parse("https://play.min.io").unwrap();
+//! let client = AsyncClient::new(endpoint, "access", "secret", s3v4::Region::UsEast1);
+//! let etag = client.put_object("bucket", "key", b"data".to_vec()).await?;
+//! let mut data = Vec::new();
+//! client.get_object("bucket", "key", &mut data).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::attributes::{
+    attributes_headers, head_headers, parse_attributes_response, parse_head_response,
+    ObjectAttributes, ObjectInfo,
+};
+use super::list::{parse_list_objects, Object};
+use super::multipart::{
+    check_part_size, complete_body, parse_complete_response, parse_list_parts, parse_list_uploads,
+    parse_upload_id, stale_uploads, CompletedPart, MultipartUploadState, Part, Upload,
+};
+use super::{
+    is_retryable, Config, PutObjectOutput, RateLimiter, UNSIGNED_PAYLOAD, VERSION_ID_HEADER,
+};
+use crate::errors::*;
+use crate::{Method, ProvideCredentials, Region};
+use futures_util::stream;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use url::Url;
+
+/// Size of the chunks of throttled uploads.
+const THROTTLED_CHUNK_SIZE: usize = 64 * 1024;
+
+// -----------------------------------------------------------------------------
+/// Async S3 client.
+///
+/// Cloning a client is cheap: clones share the same connection pool.
+#[derive(Clone)]
+pub struct AsyncClient {
+    config: Config,
+    client: reqwest::Client,
+    limiter: Option<RateLimiter>,
+}
+
+impl AsyncClient {
+    /// Create a client for the S3 service at `endpoint`, which must not include
+    /// the bucket name.
+    pub fn new(endpoint: Url, access: &str, secret: &str, region: Region) -> Self {
+        AsyncClient {
+            config: Config::new(endpoint, access, secret, region),
+            client: reqwest::Client::new(),
+            limiter: None,
+        }
+    }
+
+    /// Use the specified `reqwest` client, e.g. to configure timeouts or proxies.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Limit the bandwidth of uploads and downloads; the limiter can be shared
+    /// with other clients and its rate changed at any time.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Retrieve the credentials from `provider`, e.g. to use temporary
+    /// credentials which are rotated; see [Signer](crate::Signer).
+    pub fn with_credentials(mut self, provider: impl ProvideCredentials + 'static) -> Self {
+        self.config.set_credentials(provider);
+        self
+    }
+
+    /// Return the URL of an object.
+    pub fn object_url(&self, bucket: &str, key: &str) -> Result<Url> {
+        self.config.object_url(bucket, key)
+    }
+
+    /// Retrieve the size of an object through a `HEAD` request.
+    pub async fn content_length(&self, bucket: &str, key: &str) -> Result<u64> {
+        let url = self.object_url(bucket, key)?;
+        let response = send(self.request(&Method::Head, &url, &BTreeMap::new())?).await?;
+        header(&response, "content-length")
+            .chain_err(|| "Missing Content-Length header")?
+            .parse::<u64>()
+            .chain_err(|| "Invalid Content-Length header")
+    }
+
+    /// Download an object, writing its content to `writer`.
+    /// Return the number of bytes written.
+    pub async fn get_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<u64> {
+        let url = self.object_url(bucket, key)?;
+        let response = send(self.request(&Method::Get, &url, &BTreeMap::new())?).await?;
+        copy(response, writer, self.limiter.as_ref()).await
+    }
+
+    /// Download the bytes in `range` of an object, writing them to `writer`.
+    /// Return the number of bytes written.
+    pub async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Range<u64>,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<u64> {
+        if range.start >= range.end {
+            bail!("Empty range {:?}", range);
+        }
+        let url = self.object_url(bucket, key)?;
+        let headers = BTreeMap::from([(
+            "range".to_string(),
+            format!("bytes={}-{}", range.start, range.end - 1),
+        )]);
+        let response = send(self.request(&Method::Get, &url, &headers)?).await?;
+        if response.status().as_u16() != 206 {
+            bail!(
+                "Expected partial content (206) for range {:?}, received {}",
+                range,
+                response.status().as_u16()
+            );
+        }
+        let len = copy(response, writer, self.limiter.as_ref()).await?;
+        if len != range.end - range.start {
+            bail!(
+                "Received {} bytes for range {:?}, expected {}",
+                len,
+                range,
+                range.end - range.start
+            );
+        }
+        Ok(len)
+    }
+
+    /// Upload an object and return its ETag.
+    pub async fn put_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<String> {
+        Ok(self.put_object_versioned(bucket, key, data).await?.etag)
+    }
+
+    /// Upload an object and return its ETag and, if versioning is enabled on
+    /// the bucket, the ID of the created version.
+    pub async fn put_object_versioned(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<PutObjectOutput> {
+        let url = self.object_url(bucket, key)?;
+        let request = self.upload_request(&url, data.into())?;
+        let response = send(request).await?;
+        Ok(PutObjectOutput {
+            etag: header(&response, "etag").chain_err(|| "Missing ETag in response")?,
+            version_id: header(&response, VERSION_ID_HEADER),
+        })
+    }
+
+    /// Delete an object, or the specified version of an object.
+    pub async fn delete_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<()> {
+        let url = self.config.object_version_url(bucket, key, version_id)?;
+        send(self.request(&Method::Delete, &url, &BTreeMap::new())?).await?;
+        Ok(())
+    }
+
+    /// Retrieve the metadata of an object, or of the specified version of an
+    /// object, through a `HEAD` request.
+    pub async fn head_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<ObjectInfo> {
+        let url = self.config.object_version_url(bucket, key, version_id)?;
+        let response = send(self.request(&Method::Head, &url, &head_headers())?).await?;
+        parse_head_response(&headers(&response))
+    }
+
+    /// Retrieve the attributes of an object, or of the specified version of an
+    /// object, without downloading its content.
+    pub async fn get_object_attributes(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<ObjectAttributes> {
+        let url = self.config.attributes_url(bucket, key, version_id)?;
+        let response = send(self.request(&Method::Get, &url, &attributes_headers())?).await?;
+        let headers = headers(&response);
+        parse_attributes_response(&headers, &text(response).await?)
+    }
+
+    /// List the objects in `bucket` whose key starts with `prefix`.
+    pub async fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<Object>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let url = self
+                .config
+                .list_objects_url(bucket, prefix, token.as_deref())?;
+            let response = send(self.request(&Method::Get, &url, &BTreeMap::new())?).await?;
+            token = parse_list_objects(&text(response).await?, &mut objects)?;
+            if token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Create a multipart upload and return its upload ID.
+    pub async fn create_multipart_upload(&self, bucket: &str, key: &str) -> Result<String> {
+        let mut url = self.object_url(bucket, key)?;
+        url.set_query(Some("uploads"));
+        let response = send(self.request(&Method::Post, &url, &BTreeMap::new())?).await?;
+        parse_upload_id(&text(response).await?)
+    }
+
+    /// Upload a part and return its ETag.
+    pub async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<String> {
+        let url = self
+            .config
+            .upload_url(bucket, key, upload_id, Some(part_number))?;
+        let request = self.upload_request(&url, data.into())?;
+        let response = send(request).await?;
+        header(&response, "etag").chain_err(|| format!("Missing ETag for part {}", part_number))
+    }
+
+    /// List the parts uploaded so far.
+    pub async fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<Part>> {
+        let mut parts = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let url = self
+                .config
+                .list_parts_url(bucket, key, upload_id, marker.as_deref())?;
+            let response = send(self.request(&Method::Get, &url, &BTreeMap::new())?).await?;
+            marker = parse_list_parts(&text(response).await?, &mut parts)?;
+            if marker.is_none() {
+                break;
+            }
+        }
+        Ok(parts)
+    }
+
+    /// List the multipart uploads in progress for the objects in `bucket`
+    /// whose key starts with `prefix`.
+    pub async fn list_multipart_uploads(&self, bucket: &str, prefix: &str) -> Result<Vec<Upload>> {
+        let mut uploads = Vec::new();
+        let mut marker: Option<(String, String)> = None;
+        loop {
+            let url = self
+                .config
+                .list_uploads_url(bucket, prefix, marker.as_ref())?;
+            let response = send(self.request(&Method::Get, &url, &BTreeMap::new())?).await?;
+            marker = parse_list_uploads(&text(response).await?, &mut uploads)?;
+            if marker.is_none() {
+                break;
+            }
+        }
+        Ok(uploads)
+    }
+
+    /// Abort a multipart upload, deleting the parts uploaded so far.
+    pub async fn abort_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> Result<()> {
+        let url = self.config.upload_url(bucket, key, upload_id, None)?;
+        send(self.request(&Method::Delete, &url, &BTreeMap::new())?).await?;
+        Ok(())
+    }
+
+    /// Abort the multipart uploads initiated more than `age` ago, see
+    /// [Client::cleanup_stale_uploads](super::Client::cleanup_stale_uploads).
+    pub async fn cleanup_stale_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        age: Duration,
+    ) -> Result<Vec<Upload>> {
+        let stale = stale_uploads(self.list_multipart_uploads(bucket, prefix).await?, age)?;
+        for upload in &stale {
+            match self
+                .abort_multipart_upload(bucket, &upload.key, &upload.upload_id)
+                .await
+            {
+                // completed or aborted in the meantime
+                Err(Error(ErrorKind::HttpStatus(404, _), _)) => {}
+                r => r.chain_err(|| format!("Error aborting upload of '{}'", upload.key))?,
+            }
+        }
+        Ok(stale)
+    }
+
+    /// Complete a multipart upload and return the ETag of the object.
+    pub async fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<String> {
+        let url = self.config.upload_url(bucket, key, upload_id, None)?;
+        let request = self
+            .request(&Method::Post, &url, &BTreeMap::new())?
+            .body(complete_body(parts));
+        let response = send(request).await?;
+        parse_complete_response(&text(response).await?)
+    }
+
+    /// Create a signed `PUT` request sending `data`, throttled by the rate limiter.
+    fn upload_request(&self, url: &Url, data: Vec<u8>) -> Result<reqwest::RequestBuilder> {
+        let request = self.request(&Method::Put, url, &BTreeMap::new())?;
+        let limiter = match &self.limiter {
+            Some(limiter) => limiter.clone(),
+            None => return Ok(request.body(data)),
+        };
+        let len = data.len();
+        let chunks = stream::unfold((data, 0, limiter), |(data, start, limiter)| async move {
+            if start >= data.len() {
+                return None;
+            }
+            let end = (start + THROTTLED_CHUNK_SIZE).min(data.len());
+            limiter.wait_async((end - start) as u64).await;
+            let chunk = data[start..end].to_vec();
+            Some((Ok::<_, std::io::Error>(chunk), (data, end, limiter)))
+        });
+        Ok(request
+            .header("content-length", len)
+            .body(reqwest::Body::wrap_stream(chunks)))
+    }
+
+    /// Create a signed request, `headers` are signed and added to the request.
+    fn request(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &BTreeMap<String, String>,
+    ) -> Result<reqwest::RequestBuilder> {
+        let http_method = reqwest::Method::from_bytes(method.as_str().as_bytes())
+            .chain_err(|| format!("Invalid method {}", method))?;
+        let mut request = self.client.request(http_method, url.clone());
+        for (k, v) in self
+            .config
+            .signed_headers(method, url, headers, UNSIGNED_PAYLOAD)?
+        {
+            request = request.header(k, v);
+        }
+        Ok(request)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Async multipart upload of a seekable data source, see
+/// [MultipartUpload](super::MultipartUpload).
+pub struct AsyncMultipartUpload<'a> {
+    client: &'a AsyncClient,
+    state: MultipartUploadState,
+    retries: u32,
+}
+
+impl<'a> AsyncMultipartUpload<'a> {
+    /// Create a new multipart upload for an object of `size` bytes.
+    pub async fn start(
+        client: &'a AsyncClient,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        part_size: u64,
+    ) -> Result<AsyncMultipartUpload<'a>> {
+        check_part_size(size, part_size)?;
+        let upload_id = client.create_multipart_upload(bucket, key).await?;
+        Ok(Self::new(
+            client,
+            MultipartUploadState::new(bucket, key, upload_id, size, part_size),
+        ))
+    }
+
+    /// Resume an upload from a saved state.
+    ///
+    /// The list of uploaded parts is retrieved from the server: parts which are
+    /// missing or have an unexpected size are uploaded again. An error is
+    /// returned if the part size of the state is invalid, e.g. after it was edited.
+    pub async fn resume(
+        client: &'a AsyncClient,
+        mut state: MultipartUploadState,
+    ) -> Result<AsyncMultipartUpload<'a>> {
+        check_part_size(state.size, state.part_size)?;
+        state.set_uploaded(
+            client
+                .list_parts(&state.bucket, &state.key, &state.upload_id)
+                .await?,
+        );
+        Ok(Self::new(client, state))
+    }
+
+    fn new(client: &'a AsyncClient, state: MultipartUploadState) -> Self {
+        AsyncMultipartUpload {
+            client,
+            state,
+            retries: 2,
+        }
+    }
+
+    /// Set the number of times a part is uploaded again after a transport or
+    /// server error, defaults to 2.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Return the current state of the upload.
+    pub fn state(&self) -> &MultipartUploadState {
+        &self.state
+    }
+
+    /// Upload the next missing part, reading it from `data` at its offset.
+    /// Return `false` when all the parts have been uploaded.
+    pub async fn upload_next_part(
+        &mut self,
+        data: &mut (impl AsyncRead + AsyncSeek + Unpin),
+    ) -> Result<bool> {
+        let part_number = match self.state.next_part() {
+            Some(n) => n,
+            None => return Ok(false),
+        };
+        let (start, end) = self.state.part_range(part_number);
+        data.seek(std::io::SeekFrom::Start(start))
+            .await
+            .chain_err(|| format!("Error seeking part {}", part_number))?;
+        let mut buffer = vec![0_u8; (end - start) as usize];
+        data.read_exact(&mut buffer)
+            .await
+            .chain_err(|| format!("Error reading part {}", part_number))?;
+        let mut attempt = 0;
+        let etag = loop {
+            let result = self
+                .client
+                .upload_part(
+                    &self.state.bucket,
+                    &self.state.key,
+                    &self.state.upload_id,
+                    part_number,
+                    buffer.clone(),
+                )
+                .await;
+            match result {
+                Ok(etag) => break etag,
+                Err(err) if attempt < self.retries && is_retryable(&err) => attempt += 1,
+                Err(err) => return Err(err),
+            }
+        };
+        self.state.add_part(part_number, etag);
+        Ok(true)
+    }
+
+    /// Upload all the missing parts.
+    pub async fn upload(&mut self, data: &mut (impl AsyncRead + AsyncSeek + Unpin)) -> Result<()> {
+        while self.upload_next_part(data).await? {}
+        Ok(())
+    }
+
+    /// Complete the upload and return the ETag of the object.
+    pub async fn complete(self) -> Result<String> {
+        if let Some(n) = self.state.next_part() {
+            bail!("Cannot complete upload: part {} missing", n);
+        }
+        self.client
+            .complete_multipart_upload(
+                &self.state.bucket,
+                &self.state.key,
+                &self.state.upload_id,
+                &self.state.parts,
+            )
+            .await
+    }
+
+    /// Abort the upload, deleting the parts uploaded so far.
+    pub async fn abort(self) -> Result<()> {
+        self.client
+            .abort_multipart_upload(&self.state.bucket, &self.state.key, &self.state.upload_id)
+            .await
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Send a request, converting error status codes into [ErrorKind::HttpStatus] errors.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+    let response = request
+        .send()
+        .await
+        .chain_err(|| ErrorKind::Transport("HTTP request failed".to_string()))?;
+    let status = response.status().as_u16();
+    if status >= 400 {
+        let body = response.text().await.unwrap_or_default();
+        return Err(ErrorKind::HttpStatus(status, body).into());
+    }
+    Ok(response)
+}
+
+/// Read the body of a response as text.
+async fn text(response: reqwest::Response) -> Result<String> {
+    response
+        .text()
+        .await
+        .chain_err(|| ErrorKind::Transport("Error reading response".to_string()))
+}
+
+/// Return the value of a response header.
+fn header(response: &reqwest::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+/// Return the headers of a response, skipping values which are not text.
+fn headers(response: &reqwest::Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .collect()
+}
+
+/// Copy the body of a response to `writer`, return the number of bytes written.
+async fn copy(
+    mut response: reqwest::Response,
+    writer: &mut (impl AsyncWrite + Unpin),
+    limiter: Option<&RateLimiter>,
+) -> Result<u64> {
+    let mut len = 0;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .chain_err(|| ErrorKind::Transport("Error reading object".to_string()))?
+    {
+        if let Some(limiter) = limiter {
+            limiter.wait_async(chunk.len() as u64).await;
+        }
+        writer
+            .write_all(&chunk)
+            .await
+            .chain_err(|| "Error writing object")?;
+        len += chunk.len() as u64;
+    }
+    writer.flush().await.chain_err(|| "Error writing object")?;
+    Ok(len)
+}
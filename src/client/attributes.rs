@@ -0,0 +1,288 @@
+//! Object metadata: `HeadObject` and `GetObjectAttributes`.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/API/API_GetObjectAttributes.html)
+
+use super::multipart::parse_element;
+use super::{Client, Config, VERSION_ID_HEADER};
+use crate::errors::*;
+use crate::xml;
+use crate::{Method, METADATA_PREFIX};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Attributes requested by [Client::get_object_attributes].
+pub(crate) const OBJECT_ATTRIBUTES: &str = "ETag,Checksum,ObjectParts,StorageClass,ObjectSize";
+
+// -----------------------------------------------------------------------------
+/// Checksums of an object, base64 encoded; only the algorithm selected when
+/// uploading the object is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checksums {
+    pub crc32: Option<String>,
+    pub crc32c: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+/// Object metadata returned by [Client::head_object].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectInfo {
+    pub content_length: u64,
+    /// ETag, including quotes.
+    pub etag: String,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub content_type: Option<String>,
+    /// Storage class, `None` for the `STANDARD` class.
+    pub storage_class: Option<String>,
+    pub version_id: Option<String>,
+    pub checksums: Checksums,
+    /// User-defined metadata, without the `x-amz-meta-` prefix.
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Object attributes returned by [Client::get_object_attributes].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectAttributes {
+    /// ETag, without quotes.
+    pub etag: Option<String>,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub object_size: Option<u64>,
+    pub storage_class: Option<String>,
+    pub version_id: Option<String>,
+    pub checksums: Checksums,
+    /// Number of parts of objects created with a multipart upload.
+    pub parts_count: Option<u32>,
+}
+
+impl Client {
+    /// Retrieve the metadata of an object, or of the specified version of an
+    /// object, through a `HEAD` request.
+    pub fn head_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<ObjectInfo> {
+        let url = self.config.object_version_url(bucket, key, version_id)?;
+        let response = self.send(&Method::Head, &url, &head_headers(), None)?;
+        parse_head_response(&response.headers)
+    }
+
+    /// Retrieve the attributes of an object, or of the specified version of an
+    /// object, without downloading its content.
+    pub fn get_object_attributes(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<ObjectAttributes> {
+        let url = self.config.attributes_url(bucket, key, version_id)?;
+        let response = self.send(&Method::Get, &url, &attributes_headers(), None)?;
+        let headers = response.headers.clone();
+        parse_attributes_response(&headers, &response.into_string()?)
+    }
+}
+
+impl Config {
+    /// Return the URL of the attributes of an object.
+    pub(crate) fn attributes_url(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> Result<Url> {
+        let mut url = self.object_url(bucket, key)?;
+        // `attributes` must precede `versionId` in the query
+        url.set_query(Some("attributes"));
+        if let Some(version_id) = version_id {
+            url.query_pairs_mut().append_pair("versionId", version_id);
+        }
+        Ok(url)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Headers of `HEAD` requests: checksums are only returned when requested.
+pub(crate) fn head_headers() -> BTreeMap<String, String> {
+    let mut headers = BTreeMap::new();
+    headers.insert("x-amz-checksum-mode".to_string(), "ENABLED".to_string());
+    headers
+}
+
+/// Headers of `GetObjectAttributes` requests.
+pub(crate) fn attributes_headers() -> BTreeMap<String, String> {
+    let mut headers = BTreeMap::new();
+    headers.insert(
+        "x-amz-object-attributes".to_string(),
+        OBJECT_ATTRIBUTES.to_string(),
+    );
+    headers
+}
+
+/// Parse the headers of a `HEAD` response.
+pub(crate) fn parse_head_response(headers: &[(String, String)]) -> Result<ObjectInfo> {
+    let header = |name: &str| find_header(headers, name);
+    let metadata = headers
+        .iter()
+        .filter_map(|(k, v)| {
+            let k = k.to_ascii_lowercase();
+            k.strip_prefix(METADATA_PREFIX)
+                .map(|k| (k.to_string(), v.clone()))
+        })
+        .collect();
+    Ok(ObjectInfo {
+        content_length: header("content-length")
+            .chain_err(|| "Missing Content-Length header")?
+            .parse()
+            .chain_err(|| "Invalid Content-Length header")?,
+        etag: header("etag").chain_err(|| "Missing ETag header")?,
+        last_modified: header("last-modified")
+            .map(|v| parse_http_date(&v))
+            .transpose()?,
+        content_type: header("content-type"),
+        storage_class: header("x-amz-storage-class"),
+        version_id: header(VERSION_ID_HEADER),
+        checksums: Checksums {
+            crc32: header("x-amz-checksum-crc32"),
+            crc32c: header("x-amz-checksum-crc32c"),
+            sha1: header("x-amz-checksum-sha1"),
+            sha256: header("x-amz-checksum-sha256"),
+        },
+        metadata,
+    })
+}
+
+/// Parse the headers and body of a `GetObjectAttributes` response.
+pub(crate) fn parse_attributes_response(
+    headers: &[(String, String)],
+    body: &str,
+) -> Result<ObjectAttributes> {
+    let header = |name: &str| find_header(headers, name);
+    let checksum = xml::element(body, "Checksum").unwrap_or_default();
+    let parts_count = xml::element(body, "ObjectParts")
+        .map(|parts| parse_element(parts, "TotalPartsCount"))
+        .transpose()?;
+    Ok(ObjectAttributes {
+        etag: xml::text(body, "ETag"),
+        last_modified: header("last-modified")
+            .map(|v| parse_http_date(&v))
+            .transpose()?,
+        object_size: xml::element(body, "ObjectSize")
+            .map(|_| parse_element(body, "ObjectSize"))
+            .transpose()?,
+        storage_class: xml::text(body, "StorageClass"),
+        version_id: header(VERSION_ID_HEADER),
+        checksums: Checksums {
+            crc32: xml::text(checksum, "ChecksumCRC32"),
+            crc32c: xml::text(checksum, "ChecksumCRC32C"),
+            sha1: xml::text(checksum, "ChecksumSHA1"),
+            sha256: xml::text(checksum, "ChecksumSHA256"),
+        },
+        parts_count,
+    })
+}
+
+/// Return the value of the first header named `name`, ignoring case.
+fn find_header(headers: &[(String, String)], name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+}
+
+/// Parse a date in the format of the `Last-Modified` header,
+/// e.g. `Wed, 12 Oct 2022 17:50:00 GMT`.
+fn parse_http_date(date: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(date)
+        .map(|d| d.with_timezone(&Utc))
+        .chain_err(|| format!("Invalid date '{}'", date))
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn headers(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_head_response() -> Result<()> {
+        let info = parse_head_response(&headers(&[
+            ("Content-Length", "1234"),
+            ("ETag", "\"abc-2\""),
+            ("Last-Modified", "Wed, 12 Oct 2022 17:50:00 GMT"),
+            ("Content-Type", "text/plain"),
+            ("x-amz-version-id", "v1"),
+            ("x-amz-checksum-crc32c", "yZRlqg=="),
+            ("X-Amz-Meta-Author", "me"),
+        ]))?;
+        assert_eq!(1234, info.content_length);
+        assert_eq!("\"abc-2\"", info.etag);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2022, 10, 12, 17, 50, 0).unwrap()),
+            info.last_modified
+        );
+        assert_eq!(Some("text/plain"), info.content_type.as_deref());
+        assert_eq!(None, info.storage_class);
+        assert_eq!(Some("v1"), info.version_id.as_deref());
+        assert_eq!(Some("yZRlqg=="), info.checksums.crc32c.as_deref());
+        assert_eq!(None, info.checksums.sha256);
+        assert_eq!(Some("me"), info.metadata.get("author").map(|v| v.as_str()));
+        assert!(parse_head_response(&headers(&[("ETag", "\"a\"")])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_attributes_response() -> Result<()> {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<GetObjectAttributesResponse xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+<ETag>abc-2</ETag>
+<Checksum><ChecksumSHA256>n4bQgYhMfWWaL+qgxVrQFaO/TxsrC4Is0V1sFbDwCgg=</ChecksumSHA256></Checksum>
+<ObjectParts><TotalPartsCount>2</TotalPartsCount><PartsCount>2</PartsCount>
+<Part><ChecksumSHA256>x</ChecksumSHA256><PartNumber>1</PartNumber><Size>5242880</Size></Part>
+</ObjectParts>
+<StorageClass>STANDARD</StorageClass>
+<ObjectSize>5242890</ObjectSize>
+</GetObjectAttributesResponse>"#;
+        let attributes = parse_attributes_response(
+            &headers(&[("Last-Modified", "Wed, 12 Oct 2022 17:50:00 GMT")]),
+            body,
+        )?;
+        assert_eq!(
+            ObjectAttributes {
+                etag: Some("abc-2".to_string()),
+                last_modified: Some(Utc.with_ymd_and_hms(2022, 10, 12, 17, 50, 0).unwrap()),
+                object_size: Some(5242890),
+                storage_class: Some("STANDARD".to_string()),
+                version_id: None,
+                checksums: Checksums {
+                    sha256: Some("n4bQgYhMfWWaL+qgxVrQFaO/TxsrC4Is0V1sFbDwCgg=".to_string()),
+                    ..Default::default()
+                },
+                parts_count: Some(2),
+            },
+            attributes
+        );
+        let config = Config::new(
+            Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?,
+            "access",
+            "secret",
+            crate::Region::UsEast1,
+        );
+        assert_eq!(
+            "https://play.min.io/bucket/key?attributes&versionId=v%2B1",
+            config
+                .attributes_url("bucket", "key", Some("v+1"))?
+                .as_str()
+        );
+        Ok(())
+    }
+}
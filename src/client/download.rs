@@ -0,0 +1,194 @@
+//! Parallel ranged downloads.
+
+use super::progress::TransferEvent;
+use super::{is_retryable, Client};
+use crate::errors::*;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+// -----------------------------------------------------------------------------
+/// Options for [Client::download_parallel].
+#[derive(Clone, Debug)]
+pub struct DownloadOptions {
+    /// Size of the range requested by each `GET` request, defaults to 8 MiB.
+    pub chunk_size: u64,
+    /// Maximum number of concurrent requests, defaults to 4.
+    pub concurrency: usize,
+    /// Number of times a chunk is requested again after a transport or server
+    /// error, defaults to 2.
+    pub retries: u32,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            chunk_size: 8 * 1024 * 1024,
+            concurrency: 4,
+            retries: 2,
+        }
+    }
+}
+
+impl Client {
+    /// Download an object into a file using concurrent ranged `GET` requests.
+    ///
+    /// The object size and ETag are retrieved with a `HEAD` request, the file
+    /// is then pre-allocated and each chunk written at its offset as soon as it
+    /// is received. Chunks are requested with `If-Match: <ETag>`, so that the
+    /// download fails with an [ErrorKind::HttpStatus] 412 error instead of
+    /// mixing the content of two versions if the object is replaced meanwhile.
+    /// Return the size of the object.
+    pub fn download_parallel(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: impl AsRef<Path>,
+        options: &DownloadOptions,
+    ) -> Result<u64> {
+        self.download_parallel_with_progress(bucket, key, path, options, |_| {})
+    }
+
+    /// Download an object like [Client::download_parallel], reporting progress
+    /// to `progress`.
+    ///
+    /// Events are forwarded from the download threads and `progress` is invoked
+    /// on the calling thread; chunks are reported as parts numbered from 1, with
+    /// their bytes once received.
+    pub fn download_parallel_with_progress(
+        &self,
+        bucket: &str,
+        key: &str,
+        path: impl AsRef<Path>,
+        options: &DownloadOptions,
+        mut progress: impl FnMut(TransferEvent),
+    ) -> Result<u64> {
+        if options.chunk_size == 0 || options.concurrency == 0 {
+            bail!("Chunk size and concurrency must be greater than zero");
+        }
+        let path = path.as_ref();
+        let info = self.head_object(bucket, key, None)?;
+        let len = info.content_length;
+        let file = File::create(path).chain_err(|| format!("Error creating {:?}", path))?;
+        file.set_len(len)
+            .chain_err(|| format!("Error allocating {} bytes for {:?}", len, path))?;
+        progress(TransferEvent::Started { total: Some(len) });
+        let ranges = Arc::new(chunk_ranges(len, options.chunk_size));
+        let next = Arc::new(AtomicUsize::new(0));
+        let failed = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let workers = (0..options.concurrency.min(ranges.len()))
+            .map(|_| {
+                let (client, ranges, next, failed, sender) = (
+                    self.clone(),
+                    ranges.clone(),
+                    next.clone(),
+                    failed.clone(),
+                    sender.clone(),
+                );
+                let (bucket, key, path) = (bucket.to_string(), key.to_string(), path.to_owned());
+                let etag = info.etag.clone();
+                let retries = options.retries;
+                thread::spawn(move || -> Result<()> {
+                    // the receiver is only dropped after all the workers exit
+                    let send = |event| sender.send(event).unwrap_or(());
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .open(&path)
+                        .chain_err(|| format!("Error opening {:?}", path))?;
+                    while !failed.load(Ordering::Relaxed) {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let range = match ranges.get(index) {
+                            Some(range) => range.clone(),
+                            None => break,
+                        };
+                        let part_number = index as u32 + 1;
+                        let mut attempt = 0;
+                        loop {
+                            let result = file
+                                .seek(SeekFrom::Start(range.start))
+                                .chain_err(|| format!("Error seeking {:?}", path))
+                                .and_then(|_| {
+                                    client.get_object_range_if_match(
+                                        &bucket,
+                                        &key,
+                                        range.clone(),
+                                        Some(&etag),
+                                        &mut file,
+                                    )
+                                });
+                            match result {
+                                Ok(size) => {
+                                    // not reported while receiving, failed attempts
+                                    // would be counted too
+                                    send(TransferEvent::BytesTransferred { bytes: size });
+                                    send(TransferEvent::PartCompleted { part_number, size });
+                                    break;
+                                }
+                                Err(err) if attempt < retries && is_retryable(&err) => {
+                                    attempt += 1;
+                                    send(TransferEvent::Retry {
+                                        part_number,
+                                        attempt,
+                                        error: err.to_string(),
+                                    });
+                                }
+                                Err(err) => {
+                                    failed.store(true, Ordering::Relaxed);
+                                    return Err(err);
+                                }
+                            }
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+        for event in receiver {
+            progress(event);
+        }
+        let mut result = Ok(len);
+        for worker in workers {
+            let r = match worker.join() {
+                Ok(r) => r,
+                Err(_) => Err("Download thread panicked".into()),
+            };
+            if let (Ok(_), Err(err)) = (&result, r) {
+                result = Err(err);
+            }
+        }
+        if result.is_ok() {
+            progress(TransferEvent::Completed);
+        }
+        result
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Split `[0, len)` into consecutive ranges of `chunk_size` bytes, the last one
+/// possibly shorter.
+fn chunk_ranges(len: u64, chunk_size: u64) -> Vec<Range<u64>> {
+    (0..len)
+        .step_by(chunk_size as usize)
+        .map(|start| start..(start + chunk_size).min(len))
+        .collect()
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_ranges() {
+        assert_eq!(Vec::<Range<u64>>::new(), chunk_ranges(0, 10));
+        assert_eq!(vec![0..10], chunk_ranges(10, 10));
+        assert_eq!(vec![0..4, 4..8, 8..10], chunk_ranges(10, 4));
+    }
+}
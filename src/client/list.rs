@@ -0,0 +1,134 @@
+//! Object listing.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/API/API_ListObjectsV2.html)
+
+use super::{Client, Config};
+use crate::errors::*;
+use crate::xml;
+use crate::Method;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use url::Url;
+
+// -----------------------------------------------------------------------------
+/// Object returned by [Client::list_objects].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Object {
+    pub key: String,
+    pub size: u64,
+    /// ETag, including quotes.
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl Client {
+    /// List the objects in `bucket` whose key starts with `prefix`.
+    pub fn list_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<Object>> {
+        let mut objects = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let url = self
+                .config
+                .list_objects_url(bucket, prefix, token.as_deref())?;
+            let body = self
+                .send(&Method::Get, &url, &BTreeMap::new(), None)?
+                .into_string()?;
+            token = parse_list_objects(&body, &mut objects)?;
+            if token.is_none() {
+                break;
+            }
+        }
+        Ok(objects)
+    }
+}
+
+impl Config {
+    /// Return the URL of a page of objects, starting at continuation `token`.
+    pub(crate) fn list_objects_url(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        token: Option<&str>,
+    ) -> Result<Url> {
+        let mut url = self.object_url(bucket, "")?;
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("list-type", "2");
+            if !prefix.is_empty() {
+                query.append_pair("prefix", prefix);
+            }
+            if let Some(token) = token {
+                query.append_pair("continuation-token", token);
+            }
+        }
+        Ok(url)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Parse a page of a list objects response, appending the objects to `objects`.
+/// Return the continuation token of the next page, `None` for the last page.
+pub(crate) fn parse_list_objects(body: &str, objects: &mut Vec<Object>) -> Result<Option<String>> {
+    for object in xml::elements(body, "Contents") {
+        let last_modified =
+            xml::text(object, "LastModified").chain_err(|| "Missing LastModified")?;
+        objects.push(Object {
+            key: xml::text(object, "Key").chain_err(|| "Missing Key")?,
+            size: super::multipart::parse_element(object, "Size")?,
+            etag: xml::text(object, "ETag").chain_err(|| "Missing ETag")?,
+            last_modified: DateTime::parse_from_rfc3339(&last_modified)
+                .chain_err(|| format!("Invalid LastModified '{}'", last_modified))?
+                .with_timezone(&Utc),
+        });
+    }
+    if xml::element(body, "IsTruncated") != Some("true") {
+        return Ok(None);
+    }
+    match xml::text(body, "NextContinuationToken") {
+        Some(token) => Ok(Some(token)),
+        None => bail!("Missing NextContinuationToken in truncated response"),
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list_objects() -> Result<()> {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+<Name>bucket</Name><Prefix>dir/</Prefix><KeyCount>2</KeyCount>
+<Contents><Key>dir/a&amp;b</Key><LastModified>2023-01-02T03:04:05.000Z</LastModified><ETag>&quot;1&quot;</ETag><Size>10</Size></Contents>
+<Contents><Key>dir/c</Key><LastModified>2023-01-02T03:04:06.000Z</LastModified><ETag>"2"</ETag><Size>0</Size></Contents>
+<IsTruncated>true</IsTruncated><NextContinuationToken>abc=</NextContinuationToken>
+</ListBucketResult>"#;
+        let mut objects = Vec::new();
+        assert_eq!(
+            Some("abc=".to_string()),
+            parse_list_objects(body, &mut objects)?
+        );
+        assert_eq!(2, objects.len());
+        assert_eq!("dir/a&b", objects[0].key);
+        assert_eq!("\"1\"", objects[0].etag);
+        assert_eq!(10, objects[0].size);
+        assert_eq!(
+            "2023-01-02T03:04:06+00:00",
+            objects[1].last_modified.to_rfc3339()
+        );
+        let url = Config::new(
+            Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?,
+            "access",
+            "secret",
+            crate::Region::UsEast1,
+        )
+        .list_objects_url("bucket", "dir/", Some("abc="))?;
+        assert_eq!(
+            "https://play.min.io/bucket/?list-type=2&prefix=dir%2F&continuation-token=abc%3D",
+            url.as_str()
+        );
+        Ok(())
+    }
+}
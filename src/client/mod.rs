@@ -0,0 +1,560 @@
+//! Blocking S3 client, available with the `client` feature.
+//!
+//! Requests are sent with the `ureq` crate by default, or through any
+//! [HttpTransport] set with [Client::with_transport]. Requests are signed with
+//! a [Signer](crate::Signer), using `UNSIGNED-PAYLOAD` as the payload hash and
+//! the credentials set with [Client::with_credentials], and objects are addressed
+//! with path-style URLs built by [object_url](crate::object_url).
+//!
+//! ```no_run
+//! # fn main() -> s3v4::Result<()> {
+//! use s3v4::client::{Client, DownloadOptions};
+//! let endpoint = url::Url::parse("https://play.min.io").unwrap();
+//! let client = Client::new(endpoint, "access", "secret", s3v4::Region::UsEast1);
+//! let len = client.download_parallel("bucket", "key", "out.bin", &DownloadOptions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "async")]
+mod async_client;
+mod attributes;
+mod download;
+mod list;
+mod multipart;
+mod progress;
+#[cfg(feature = "event-stream")]
+mod select;
+mod throttle;
+mod transport;
+mod verify;
+
+#[cfg(feature = "async")]
+pub use async_client::{AsyncClient, AsyncMultipartUpload};
+pub use attributes::{Checksums, ObjectAttributes, ObjectInfo};
+pub use download::DownloadOptions;
+pub use list::Object;
+pub use multipart::{
+    CompletedPart, MultipartUpload, MultipartUploadState, Part, Upload, MAX_PARTS, MAX_PART_SIZE,
+    MIN_PART_SIZE,
+};
+pub use progress::TransferEvent;
+pub use throttle::RateLimiter;
+pub use transport::{HttpBody, HttpRequest, HttpResponse, HttpTransport};
+pub use verify::{ChecksumAlgorithm, VerifiedObject};
+
+use crate::errors::*;
+use crate::{object_url, Addressing, Credentials, Method, ProvideCredentials, Region, Signer};
+use progress::{ProgressReader, ProgressWriter};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::sync::Arc;
+use throttle::Throttled;
+use url::Url;
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+/// Header containing the ID of the version created by an upload.
+const VERSION_ID_HEADER: &str = "x-amz-version-id";
+
+// -----------------------------------------------------------------------------
+/// Result of an upload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PutObjectOutput {
+    /// Quoted ETag of the object.
+    pub etag: String,
+    /// ID of the created version, `None` if versioning is not enabled.
+    pub version_id: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+/// Blocking S3 client.
+///
+/// Cloning a client is cheap: clones share the same transport and connection pool.
+#[derive(Clone)]
+pub struct Client {
+    config: Config,
+    transport: Arc<dyn HttpTransport>,
+    limiter: Option<RateLimiter>,
+}
+
+impl Client {
+    /// Create a client for the S3 service at `endpoint`, which must not include
+    /// the bucket name.
+    pub fn new(endpoint: Url, access: &str, secret: &str, region: Region) -> Self {
+        Client {
+            config: Config::new(endpoint, access, secret, region),
+            transport: Arc::new(ureq::Agent::new()),
+            limiter: None,
+        }
+    }
+
+    /// Use the specified `ureq` agent, e.g. to configure timeouts or proxies.
+    pub fn with_agent(self, agent: ureq::Agent) -> Self {
+        self.with_transport(agent)
+    }
+
+    /// Send requests through the specified transport.
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Limit the bandwidth of uploads and downloads; the limiter can be shared
+    /// with other clients and its rate changed at any time.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Retrieve the credentials from `provider`, e.g. to use temporary
+    /// credentials which are rotated; see [Signer](crate::Signer).
+    pub fn with_credentials(mut self, provider: impl ProvideCredentials + 'static) -> Self {
+        self.config.set_credentials(provider);
+        self
+    }
+
+    /// Return the URL of an object.
+    pub fn object_url(&self, bucket: &str, key: &str) -> Result<Url> {
+        self.config.object_url(bucket, key)
+    }
+
+    /// Retrieve the size of an object through a `HEAD` request.
+    pub fn content_length(&self, bucket: &str, key: &str) -> Result<u64> {
+        let url = self.object_url(bucket, key)?;
+        let response = self.send(&Method::Head, &url, &BTreeMap::new(), None)?;
+        response
+            .header("content-length")
+            .chain_err(|| "Missing Content-Length header")?
+            .parse::<u64>()
+            .chain_err(|| "Invalid Content-Length header")
+    }
+
+    /// Download an object, writing its content to `writer`.
+    /// Return the number of bytes written.
+    pub fn get_object(&self, bucket: &str, key: &str, writer: &mut impl Write) -> Result<u64> {
+        self.get_object_with_progress(bucket, key, writer, |_| {})
+    }
+
+    /// Download an object like [Client::get_object], reporting progress to `progress`.
+    pub fn get_object_with_progress(
+        &self,
+        bucket: &str,
+        key: &str,
+        writer: &mut impl Write,
+        mut progress: impl FnMut(TransferEvent),
+    ) -> Result<u64> {
+        let url = self.object_url(bucket, key)?;
+        let mut response = self.send(&Method::Get, &url, &BTreeMap::new(), None)?;
+        let total = response
+            .header("content-length")
+            .and_then(|len| len.parse().ok());
+        progress(TransferEvent::Started { total });
+        let len = copy_body(
+            &mut response.body,
+            &mut Throttled::new(
+                ProgressWriter::new(writer, &mut progress),
+                self.limiter.clone(),
+            ),
+        )?;
+        progress(TransferEvent::Completed);
+        Ok(len)
+    }
+
+    /// Download the bytes in `range` of an object, writing them to `writer`.
+    /// Return the number of bytes written.
+    pub fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Range<u64>,
+        writer: &mut impl Write,
+    ) -> Result<u64> {
+        self.get_object_range_if_match(bucket, key, range, None, writer)
+    }
+
+    /// Download a range like [Client::get_object_range], only if the ETag of
+    /// the object is `etag`: an [ErrorKind::HttpStatus] 412 error is returned
+    /// if the object was replaced.
+    pub(crate) fn get_object_range_if_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        range: Range<u64>,
+        etag: Option<&str>,
+        writer: &mut impl Write,
+    ) -> Result<u64> {
+        if range.start >= range.end {
+            bail!("Empty range {:?}", range);
+        }
+        let url = self.object_url(bucket, key)?;
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            "range".to_string(),
+            format!("bytes={}-{}", range.start, range.end - 1),
+        );
+        if let Some(etag) = etag {
+            headers.insert("if-match".to_string(), etag.to_string());
+        }
+        let mut response = self.send(&Method::Get, &url, &headers, None)?;
+        if response.status != 206 {
+            bail!(
+                "Expected partial content (206) for range {:?}, received {}",
+                range,
+                response.status
+            );
+        }
+        let len = copy_body(
+            &mut response.body,
+            &mut Throttled::new(writer, self.limiter.clone()),
+        )?;
+        if len != range.end - range.start {
+            return Err(ErrorKind::Transport(format!(
+                "Received {} bytes for range {:?}, expected {}",
+                len,
+                range,
+                range.end - range.start
+            ))
+            .into());
+        }
+        Ok(len)
+    }
+
+    /// Upload an object and return its ETag.
+    pub fn put_object(&self, bucket: &str, key: &str, data: &[u8]) -> Result<String> {
+        self.put_object_with_progress(bucket, key, data, |_| {})
+    }
+
+    /// Upload an object like [Client::put_object], reporting progress to `progress`.
+    pub fn put_object_with_progress(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: &[u8],
+        progress: impl FnMut(TransferEvent),
+    ) -> Result<String> {
+        Ok(self.upload(bucket, key, data, progress)?.etag)
+    }
+
+    /// Upload an object and return its ETag and, if versioning is enabled on
+    /// the bucket, the ID of the created version.
+    pub fn put_object_versioned(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: &[u8],
+    ) -> Result<PutObjectOutput> {
+        self.upload(bucket, key, data, |_| {})
+    }
+
+    /// Delete an object, or the specified version of an object.
+    ///
+    /// Without version ID, deleting an object from a versioned bucket only
+    /// adds a delete marker.
+    pub fn delete_object(&self, bucket: &str, key: &str, version_id: Option<&str>) -> Result<()> {
+        let url = self.config.object_version_url(bucket, key, version_id)?;
+        self.send(&Method::Delete, &url, &BTreeMap::new(), None)?;
+        Ok(())
+    }
+
+    fn upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: &[u8],
+        mut progress: impl FnMut(TransferEvent),
+    ) -> Result<PutObjectOutput> {
+        let url = self.object_url(bucket, key)?;
+        progress(TransferEvent::Started {
+            total: Some(data.len() as u64),
+        });
+        let body = self.body(ProgressReader::new(data, &mut progress), data.len() as u64);
+        let response = self.send(&Method::Put, &url, &BTreeMap::new(), Some(body))?;
+        let output = PutObjectOutput {
+            etag: response
+                .header("etag")
+                .map(|etag| etag.to_string())
+                .chain_err(|| "Missing ETag in response")?,
+            version_id: response.header(VERSION_ID_HEADER).map(|v| v.to_string()),
+        };
+        progress(TransferEvent::Completed);
+        Ok(output)
+    }
+
+    /// Sign and send a request, `headers` are signed and added to the request.
+    /// Error status codes are converted into [ErrorKind::HttpStatus] errors.
+    fn send(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &BTreeMap<String, String>,
+        body: Option<HttpBody<'_>>,
+    ) -> Result<HttpResponse> {
+        self.send_with_payload_hash(method, url, headers, body, UNSIGNED_PAYLOAD)
+    }
+
+    /// Send a request like [Client::send], signing the hash of the body.
+    fn send_with_payload_hash(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &BTreeMap<String, String>,
+        body: Option<HttpBody<'_>>,
+        payload_hash: &str,
+    ) -> Result<HttpResponse> {
+        let request = HttpRequest {
+            method: method.clone(),
+            url: url.clone(),
+            headers: self
+                .config
+                .signed_headers(method, url, headers, payload_hash)?,
+            body,
+        };
+        let response = self.transport.execute(request)?;
+        if response.status >= 400 {
+            let status = response.status;
+            let body = response.into_string().unwrap_or_default();
+            return Err(ErrorKind::HttpStatus(status, body).into());
+        }
+        Ok(response)
+    }
+
+    /// Return a request body of `len` bytes, throttled by the rate limiter.
+    fn body<'a>(&self, reader: impl std::io::Read + 'a, len: u64) -> HttpBody<'a> {
+        HttpBody {
+            reader: Box::new(Throttled::new(reader, self.limiter.clone())),
+            len,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Endpoint and credentials, shared by the blocking and async clients.
+#[derive(Clone)]
+struct Config {
+    endpoint: Url,
+    signer: Signer,
+}
+
+impl Config {
+    fn new(endpoint: Url, access: &str, secret: &str, region: Region) -> Self {
+        Config {
+            endpoint,
+            signer: Signer::new(Credentials::new(access, secret), region, "s3"),
+        }
+    }
+
+    /// Sign requests with the credentials returned by `provider`.
+    fn set_credentials(&mut self, provider: impl ProvideCredentials + 'static) {
+        self.signer = Signer::new(provider, self.signer.region().clone(), "s3");
+    }
+
+    /// Return the path-style URL of an object.
+    fn object_url(&self, bucket: &str, key: &str) -> Result<Url> {
+        object_url(&self.endpoint, bucket, key, Addressing::Path)
+    }
+
+    /// Return the URL of the specified version of an object, or of the object
+    /// itself if `version_id` is `None`.
+    fn object_version_url(&self, bucket: &str, key: &str, version_id: Option<&str>) -> Result<Url> {
+        let mut url = self.object_url(bucket, key)?;
+        if let Some(version_id) = version_id {
+            url.query_pairs_mut().append_pair("versionId", version_id);
+        }
+        Ok(url)
+    }
+
+    /// Sign a request and return the headers to send: the signature headers
+    /// followed by `headers`, which are signed as well. `payload_hash` is
+    /// usually `UNSIGNED-PAYLOAD`.
+    fn signed_headers(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &BTreeMap<String, String>,
+        payload_hash: &str,
+    ) -> Result<Vec<(String, String)>> {
+        self.signer.sign(method, url, headers, payload_hash)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Return `true` for errors which might not occur again if the request is
+/// retried: transport errors, server (5xx) errors and throttling (429).
+fn is_retryable(err: &Error) -> bool {
+    match err.kind() {
+        ErrorKind::Transport(_) => true,
+        ErrorKind::HttpStatus(status, _) => *status == 429 || (500..600).contains(status),
+        _ => false,
+    }
+}
+
+/// Copy a response body to `writer`, return the number of bytes written.
+/// Only errors reading the body are [ErrorKind::Transport] errors.
+fn copy_body(body: &mut impl Read, writer: &mut impl Write) -> Result<u64> {
+    let mut buffer = vec![0_u8; 64 * 1024];
+    let mut len = 0;
+    loop {
+        let n = match body.read(&mut buffer) {
+            Ok(0) => return Ok(len),
+            Ok(n) => n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                return Err(err)
+                    .chain_err(|| ErrorKind::Transport("Error reading object".to_string()))
+            }
+        };
+        writer
+            .write_all(&buffer[..n])
+            .chain_err(|| "Error writing object")?;
+        len += n as u64;
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::sync::Mutex;
+
+    /// Request line, headers and body of a recorded request.
+    type Recorded = (String, Vec<(String, String)>, Vec<u8>);
+
+    /// Transport recording the requests and replying with a canned response.
+    struct FakeTransport {
+        status: u16,
+        body: &'static str,
+        requests: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    impl HttpTransport for FakeTransport {
+        fn execute(&self, request: HttpRequest<'_>) -> Result<HttpResponse> {
+            let mut body = Vec::new();
+            if let Some(mut b) = request.body {
+                b.reader.read_to_end(&mut body).chain_err(|| "read")?;
+                assert_eq!(b.len, body.len() as u64);
+            }
+            self.requests.lock().unwrap().push((
+                format!("{} {}", request.method, request.url),
+                request.headers,
+                body,
+            ));
+            Ok(HttpResponse {
+                status: self.status,
+                headers: vec![
+                    ("ETag".to_string(), "\"e\"".to_string()),
+                    ("x-amz-version-id".to_string(), "v1".to_string()),
+                ],
+                body: Box::new(self.body.as_bytes()),
+            })
+        }
+    }
+
+    fn fake_client(status: u16, body: &'static str) -> (Client, FakeTransport) {
+        let transport = FakeTransport {
+            status,
+            body,
+            requests: Arc::new(Mutex::new(Vec::new())),
+        };
+        let client = Client::new(
+            Url::parse("https://play.min.io").unwrap(),
+            "access",
+            "secret",
+            Region::UsEast1,
+        )
+        .with_transport(FakeTransport {
+            requests: transport.requests.clone(),
+            ..transport
+        });
+        (client, transport)
+    }
+
+    #[test]
+    fn test_transport() -> Result<()> {
+        let (client, transport) = fake_client(200, "");
+        assert_eq!("\"e\"", client.put_object("bucket", "a b", b"data")?);
+        let requests = transport.requests.lock().unwrap();
+        let (request, headers, body) = &requests[0];
+        assert_eq!("PUT https://play.min.io/bucket/a%20b", request);
+        assert_eq!(b"data", &body[..]);
+        assert!(headers
+            .iter()
+            .any(|(k, v)| k == "authorization" && v.starts_with("AWS4-HMAC-SHA256 ")));
+        drop(requests);
+        assert_eq!(
+            PutObjectOutput {
+                etag: "\"e\"".to_string(),
+                version_id: Some("v1".to_string()),
+            },
+            client.put_object_versioned("bucket", "key", b"")?
+        );
+        client.delete_object("bucket", "key", Some("3/L4kq+"))?;
+        assert_eq!(
+            "DELETE https://play.min.io/bucket/key?versionId=3%2FL4kq%2B",
+            transport.requests.lock().unwrap()[2].0
+        );
+        let (client, _) = fake_client(404, "<Error><Code>NoSuchKey</Code></Error>");
+        match client.get_object("bucket", "key", &mut Vec::new()) {
+            Err(Error(ErrorKind::HttpStatus(404, body), _)) => assert!(body.contains("NoSuchKey")),
+            r => panic!("unexpected result {:?}", r),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_object_range_if_match() -> Result<()> {
+        let (client, transport) =
+            fake_client(412, "<Error><Code>PreconditionFailed</Code></Error>");
+        match client.get_object_range_if_match(
+            "bucket",
+            "key",
+            0..4,
+            Some("\"e\""),
+            &mut Vec::new(),
+        ) {
+            Err(err @ Error(ErrorKind::HttpStatus(412, _), _)) => assert!(!is_retryable(&err)),
+            r => panic!("unexpected result {:?}", r),
+        }
+        let requests = transport.requests.lock().unwrap();
+        assert!(requests[0]
+            .1
+            .iter()
+            .any(|(k, v)| k == "if-match" && v == "\"e\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let status = |status| Error::from(ErrorKind::HttpStatus(status, String::new()));
+        assert!(is_retryable(&status(500)));
+        assert!(is_retryable(&status(503)));
+        assert!(is_retryable(&status(429)));
+        assert!(!is_retryable(&status(403)));
+        assert!(!is_retryable(&status(412)));
+        assert!(is_retryable(
+            &ErrorKind::Transport("reset".to_string()).into()
+        ));
+        assert!(!is_retryable(&"Error seeking part 1".into()));
+        // only errors reading the response are transport errors
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::ConnectionReset.into())
+            }
+        }
+        impl Write for Failing {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::Other.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let err = copy_body(&mut Failing, &mut Vec::new()).unwrap_err();
+        assert!(is_retryable(&err));
+        let err = copy_body(&mut &b"data"[..], &mut Failing).unwrap_err();
+        assert!(!is_retryable(&err));
+    }
+}
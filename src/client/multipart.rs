@@ -0,0 +1,746 @@
+//! Resumable multipart uploads.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/userguide/mpuoverview.html)
+
+use super::progress::{ProgressReader, TransferEvent};
+use super::{is_retryable, Client, Config};
+use crate::errors::*;
+use crate::xml;
+use crate::Method;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+use url::Url;
+
+/// Minimum size of all parts but the last one.
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+/// Maximum size of a part.
+pub const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+/// Maximum number of parts in a multipart upload.
+pub const MAX_PARTS: u64 = 10_000;
+
+// -----------------------------------------------------------------------------
+/// Part successfully uploaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompletedPart {
+    /// Part number, starting from 1.
+    pub part_number: u32,
+    /// ETag returned by the server, including quotes.
+    pub etag: String,
+}
+
+// -----------------------------------------------------------------------------
+/// State of a multipart upload.
+///
+/// With the `serde` feature the state can be serialized after each part is
+/// uploaded and used to resume the upload with [MultipartUpload::resume].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultipartUploadState {
+    pub bucket: String,
+    pub key: String,
+    /// Upload ID returned by the server when the upload is created.
+    pub upload_id: String,
+    /// Size of each part, except for the last one.
+    pub part_size: u64,
+    /// Total size of the object.
+    pub size: u64,
+    /// Uploaded parts, sorted by part number.
+    pub parts: Vec<CompletedPart>,
+}
+
+impl MultipartUploadState {
+    /// Return the number of parts of the object, at least one.
+    /// The part size must not be zero, as checked when an upload is started
+    /// or resumed.
+    pub fn num_parts(&self) -> u32 {
+        part_count(self.size, self.part_size).max(1) as u32
+    }
+
+    /// Return the byte range of a part.
+    pub(crate) fn part_range(&self, part_number: u32) -> (u64, u64) {
+        let start = (part_number as u64 - 1) * self.part_size;
+        (start, (start + self.part_size).min(self.size))
+    }
+
+    /// Return the first part not uploaded yet.
+    pub(crate) fn next_part(&self) -> Option<u32> {
+        (1..=self.num_parts()).find(|n| !self.parts.iter().any(|p| p.part_number == *n))
+    }
+
+    /// Create the state of a new upload.
+    pub(crate) fn new(
+        bucket: &str,
+        key: &str,
+        upload_id: String,
+        size: u64,
+        part_size: u64,
+    ) -> Self {
+        MultipartUploadState {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            upload_id,
+            part_size,
+            size,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Replace the completed parts with the parts listed by the server, skipping
+    /// parts which are out of range or have an unexpected size.
+    pub(crate) fn set_uploaded(&mut self, uploaded: Vec<Part>) {
+        let mut parts = uploaded
+            .into_iter()
+            .filter(|p| {
+                p.part_number >= 1 && p.part_number <= self.num_parts() && {
+                    let (start, end) = self.part_range(p.part_number);
+                    p.size == end - start
+                }
+            })
+            .map(|p| CompletedPart {
+                part_number: p.part_number,
+                etag: p.etag,
+            })
+            .collect::<Vec<_>>();
+        parts.sort_by_key(|p| p.part_number);
+        self.parts = parts;
+    }
+
+    /// Record a completed part.
+    pub(crate) fn add_part(&mut self, part_number: u32, etag: String) {
+        let index = self.parts.partition_point(|p| p.part_number < part_number);
+        self.parts
+            .insert(index, CompletedPart { part_number, etag });
+    }
+
+    /// Return the number of bytes not uploaded yet.
+    pub(crate) fn remaining(&self) -> u64 {
+        let uploaded = self
+            .parts
+            .iter()
+            .filter(|p| p.part_number <= self.num_parts())
+            .map(|p| {
+                let (start, end) = self.part_range(p.part_number);
+                end - start
+            })
+            .sum::<u64>();
+        self.size - uploaded
+    }
+}
+
+/// Check that an object of `size` bytes can be uploaded in parts of `part_size` bytes.
+pub(crate) fn check_part_size(size: u64, part_size: u64) -> Result<()> {
+    if part_size < MIN_PART_SIZE {
+        bail!(
+            "Part size {} is less than the minimum ({})",
+            part_size,
+            MIN_PART_SIZE
+        );
+    }
+    if part_size > MAX_PART_SIZE {
+        bail!(
+            "Part size {} is greater than the maximum ({})",
+            part_size,
+            MAX_PART_SIZE
+        );
+    }
+    if part_count(size, part_size) > MAX_PARTS {
+        bail!(
+            "Part size {} too small for {} bytes: more than {} parts",
+            part_size,
+            size,
+            MAX_PARTS
+        );
+    }
+    Ok(())
+}
+
+/// Return the number of parts of `size` bytes, without overflowing.
+fn part_count(size: u64, part_size: u64) -> u64 {
+    size / part_size + u64::from(size % part_size != 0)
+}
+
+// -----------------------------------------------------------------------------
+/// Multipart upload of a seekable data source, e.g. a file.
+///
+/// Parts are uploaded sequentially, one per call to [MultipartUpload::upload_next_part];
+/// the state can be saved between calls and used to resume the upload after a
+/// crash:
+///
+/// ```no_run
+/// # fn main() -> s3v4::Result<()> {
+/// # let endpoint = url::Url::parse("https://play.min.io").unwrap();
+/// # let client = s3v4::client::Client::new(endpoint, "access", "secret", s3v4::Region::UsEast1);
+/// # fn save(_: &s3v4::client::MultipartUploadState) {}
+/// use s3v4::client::{MultipartUpload, MIN_PART_SIZE};
+/// let mut file = std::fs::File::open("data.bin").unwrap();
+/// let size = file.metadata().unwrap().len();
+/// let mut upload = MultipartUpload::start(&client, "bucket", "key", size, MIN_PART_SIZE)?;
+/// while upload.upload_next_part(&mut file)? {
+///     save(upload.state());
+/// }
+/// let etag = upload.complete()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultipartUpload<'a> {
+    client: &'a Client,
+    state: MultipartUploadState,
+    retries: u32,
+    progress: Box<dyn FnMut(TransferEvent) + 'a>,
+}
+
+impl<'a> MultipartUpload<'a> {
+    /// Create a new multipart upload for an object of `size` bytes.
+    pub fn start(
+        client: &'a Client,
+        bucket: &str,
+        key: &str,
+        size: u64,
+        part_size: u64,
+    ) -> Result<Self> {
+        check_part_size(size, part_size)?;
+        let upload_id = client.create_multipart_upload(bucket, key)?;
+        Ok(Self::new(
+            client,
+            MultipartUploadState::new(bucket, key, upload_id, size, part_size),
+        ))
+    }
+
+    /// Resume an upload from a saved state.
+    ///
+    /// The list of uploaded parts is retrieved from the server: parts which are
+    /// missing or have an unexpected size are uploaded again. An error is
+    /// returned if the part size of the state is invalid, e.g. after it was edited.
+    pub fn resume(client: &'a Client, mut state: MultipartUploadState) -> Result<Self> {
+        check_part_size(state.size, state.part_size)?;
+        state.set_uploaded(client.list_parts(&state.bucket, &state.key, &state.upload_id)?);
+        Ok(Self::new(client, state))
+    }
+
+    fn new(client: &'a Client, state: MultipartUploadState) -> Self {
+        MultipartUpload {
+            client,
+            state,
+            retries: 2,
+            progress: Box::new(|_| {}),
+        }
+    }
+
+    /// Set the number of times a part is uploaded again after a transport or
+    /// server error, defaults to 2.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Report progress to `progress`: bytes sent, completed parts and retries.
+    /// [TransferEvent::Started] is reported by [MultipartUpload::upload] with
+    /// the size of the parts not uploaded yet.
+    pub fn with_progress(mut self, progress: impl FnMut(TransferEvent) + 'a) -> Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Return the current state of the upload.
+    pub fn state(&self) -> &MultipartUploadState {
+        &self.state
+    }
+
+    /// Upload the next missing part, reading it from `data` at its offset.
+    /// Return `false` when all the parts have been uploaded.
+    pub fn upload_next_part(&mut self, data: &mut (impl Read + Seek)) -> Result<bool> {
+        let part_number = match self.state.next_part() {
+            Some(n) => n,
+            None => return Ok(false),
+        };
+        let (start, end) = self.state.part_range(part_number);
+        data.seek(SeekFrom::Start(start))
+            .chain_err(|| format!("Error seeking part {}", part_number))?;
+        let mut buffer = vec![0_u8; (end - start) as usize];
+        data.read_exact(&mut buffer)
+            .chain_err(|| format!("Error reading part {}", part_number))?;
+        let mut attempt = 0;
+        let etag = loop {
+            let result = self.client.upload_part(
+                &self.state.bucket,
+                &self.state.key,
+                &self.state.upload_id,
+                part_number,
+                &buffer,
+            );
+            match result {
+                Ok(etag) => break etag,
+                Err(err) if attempt < self.retries && is_retryable(&err) => {
+                    attempt += 1;
+                    (self.progress)(TransferEvent::Retry {
+                        part_number,
+                        attempt,
+                        error: err.to_string(),
+                    });
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        // not reported while sending, failed attempts would be counted too
+        (self.progress)(TransferEvent::BytesTransferred { bytes: end - start });
+        (self.progress)(TransferEvent::PartCompleted {
+            part_number,
+            size: end - start,
+        });
+        self.state.add_part(part_number, etag);
+        Ok(true)
+    }
+
+    /// Upload all the missing parts.
+    pub fn upload(&mut self, data: &mut (impl Read + Seek)) -> Result<()> {
+        (self.progress)(TransferEvent::Started {
+            total: Some(self.state.remaining()),
+        });
+        while self.upload_next_part(data)? {}
+        Ok(())
+    }
+
+    /// Complete the upload and return the ETag of the object.
+    pub fn complete(mut self) -> Result<String> {
+        if let Some(n) = self.state.next_part() {
+            bail!("Cannot complete upload: part {} missing", n);
+        }
+        let etag = self.client.complete_multipart_upload(
+            &self.state.bucket,
+            &self.state.key,
+            &self.state.upload_id,
+            &self.state.parts,
+        )?;
+        (self.progress)(TransferEvent::Completed);
+        Ok(etag)
+    }
+
+    /// Abort the upload, deleting the parts uploaded so far.
+    pub fn abort(self) -> Result<()> {
+        self.client.abort_multipart_upload(
+            &self.state.bucket,
+            &self.state.key,
+            &self.state.upload_id,
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Part returned by [Client::list_parts].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    pub part_number: u32,
+    pub etag: String,
+    pub size: u64,
+}
+
+/// Multipart upload in progress, returned by [Client::list_multipart_uploads].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Upload {
+    pub key: String,
+    pub upload_id: String,
+    pub initiated: DateTime<Utc>,
+}
+
+impl Client {
+    /// Create a multipart upload and return its upload ID.
+    pub fn create_multipart_upload(&self, bucket: &str, key: &str) -> Result<String> {
+        let mut url = self.object_url(bucket, key)?;
+        url.set_query(Some("uploads"));
+        let body = self
+            .send(&Method::Post, &url, &BTreeMap::new(), None)?
+            .into_string()?;
+        parse_upload_id(&body)
+    }
+
+    /// Upload a part and return its ETag.
+    pub fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: &[u8],
+    ) -> Result<String> {
+        self.upload_part_with_progress(bucket, key, upload_id, part_number, data, |_| {})
+    }
+
+    /// Upload a part like [Client::upload_part], reporting the bytes sent to `progress`.
+    pub fn upload_part_with_progress(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        data: &[u8],
+        progress: impl FnMut(TransferEvent),
+    ) -> Result<String> {
+        let url = self
+            .config
+            .upload_url(bucket, key, upload_id, Some(part_number))?;
+        let body = self.body(ProgressReader::new(data, progress), data.len() as u64);
+        let response = self.send(&Method::Put, &url, &BTreeMap::new(), Some(body))?;
+        response
+            .header("etag")
+            .map(|etag| etag.to_string())
+            .chain_err(|| format!("Missing ETag for part {}", part_number))
+    }
+
+    /// List the parts uploaded so far.
+    pub fn list_parts(&self, bucket: &str, key: &str, upload_id: &str) -> Result<Vec<Part>> {
+        let mut parts = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let url = self
+                .config
+                .list_parts_url(bucket, key, upload_id, marker.as_deref())?;
+            let body = self
+                .send(&Method::Get, &url, &BTreeMap::new(), None)?
+                .into_string()?;
+            marker = parse_list_parts(&body, &mut parts)?;
+            if marker.is_none() {
+                break;
+            }
+        }
+        Ok(parts)
+    }
+
+    /// List the multipart uploads in progress for the objects in `bucket`
+    /// whose key starts with `prefix`.
+    pub fn list_multipart_uploads(&self, bucket: &str, prefix: &str) -> Result<Vec<Upload>> {
+        let mut uploads = Vec::new();
+        let mut marker: Option<(String, String)> = None;
+        loop {
+            let url = self
+                .config
+                .list_uploads_url(bucket, prefix, marker.as_ref())?;
+            let body = self
+                .send(&Method::Get, &url, &BTreeMap::new(), None)?
+                .into_string()?;
+            marker = parse_list_uploads(&body, &mut uploads)?;
+            if marker.is_none() {
+                break;
+            }
+        }
+        Ok(uploads)
+    }
+
+    /// Abort a multipart upload, deleting the parts uploaded so far.
+    pub fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        let url = self.config.upload_url(bucket, key, upload_id, None)?;
+        self.send(&Method::Delete, &url, &BTreeMap::new(), None)?;
+        Ok(())
+    }
+
+    /// Abort the multipart uploads for the objects in `bucket` whose key starts
+    /// with `prefix` initiated more than `age` ago, and return them.
+    ///
+    /// Parts of abandoned uploads are stored, and billed, until the upload is
+    /// aborted: call this function periodically unless the bucket has a lifecycle
+    /// rule aborting incomplete uploads.
+    pub fn cleanup_stale_uploads(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        age: Duration,
+    ) -> Result<Vec<Upload>> {
+        let stale = stale_uploads(self.list_multipart_uploads(bucket, prefix)?, age)?;
+        for upload in &stale {
+            match self.abort_multipart_upload(bucket, &upload.key, &upload.upload_id) {
+                // completed or aborted in the meantime
+                Err(Error(ErrorKind::HttpStatus(404, _), _)) => {}
+                r => r.chain_err(|| format!("Error aborting upload of '{}'", upload.key))?,
+            }
+        }
+        Ok(stale)
+    }
+
+    /// Complete a multipart upload and return the ETag of the object.
+    pub fn complete_multipart_upload(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        parts: &[CompletedPart],
+    ) -> Result<String> {
+        let url = self.config.upload_url(bucket, key, upload_id, None)?;
+        let body = complete_body(parts);
+        let response = self.send(
+            &Method::Post,
+            &url,
+            &BTreeMap::new(),
+            Some(self.body(body.as_bytes(), body.len() as u64)),
+        )?;
+        parse_complete_response(&response.into_string()?)
+    }
+}
+
+impl Config {
+    /// Return the URL of a multipart upload or of one of its parts.
+    pub(crate) fn upload_url(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: Option<u32>,
+    ) -> Result<Url> {
+        let mut url = self.object_url(bucket, key)?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(n) = part_number {
+                query.append_pair("partNumber", &n.to_string());
+            }
+            query.append_pair("uploadId", upload_id);
+        }
+        Ok(url)
+    }
+
+    /// Return the URL listing the parts of an upload, starting after `marker`.
+    pub(crate) fn list_parts_url(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        marker: Option<&str>,
+    ) -> Result<Url> {
+        let mut url = self.upload_url(bucket, key, upload_id, None)?;
+        if let Some(marker) = marker {
+            url.query_pairs_mut()
+                .append_pair("part-number-marker", marker);
+        }
+        Ok(url)
+    }
+
+    /// Return the URL listing the uploads in progress, starting after the key
+    /// and upload ID in `marker`.
+    pub(crate) fn list_uploads_url(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        marker: Option<&(String, String)>,
+    ) -> Result<Url> {
+        let mut url = self.object_url(bucket, "")?;
+        url.set_query(Some("uploads"));
+        {
+            let mut query = url.query_pairs_mut();
+            if !prefix.is_empty() {
+                query.append_pair("prefix", prefix);
+            }
+            if let Some((key_marker, upload_id_marker)) = marker {
+                query.append_pair("key-marker", key_marker);
+                query.append_pair("upload-id-marker", upload_id_marker);
+            }
+        }
+        Ok(url)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Parse the response to a create multipart upload request.
+pub(crate) fn parse_upload_id(body: &str) -> Result<String> {
+    xml::text(body, "UploadId").chain_err(|| "Missing UploadId in response")
+}
+
+/// Parse a page of a list parts response, appending the parts to `parts`.
+/// Return the marker of the next page, `None` for the last page.
+pub(crate) fn parse_list_parts(body: &str, parts: &mut Vec<Part>) -> Result<Option<String>> {
+    for part in xml::elements(body, "Part") {
+        parts.push(Part {
+            part_number: parse_element(part, "PartNumber")?,
+            etag: xml::text(part, "ETag").chain_err(|| "Missing ETag")?,
+            size: parse_element(part, "Size")?,
+        });
+    }
+    if xml::element(body, "IsTruncated") != Some("true") {
+        return Ok(None);
+    }
+    match xml::text(body, "NextPartNumberMarker") {
+        Some(marker) => Ok(Some(marker)),
+        None => bail!("Missing NextPartNumberMarker in truncated response"),
+    }
+}
+
+/// Parse a page of a list multipart uploads response, appending the uploads to
+/// `uploads`. Return the key and upload ID markers of the next page, `None` for
+/// the last page.
+pub(crate) fn parse_list_uploads(
+    body: &str,
+    uploads: &mut Vec<Upload>,
+) -> Result<Option<(String, String)>> {
+    for upload in xml::elements(body, "Upload") {
+        let initiated = xml::text(upload, "Initiated").chain_err(|| "Missing Initiated")?;
+        uploads.push(Upload {
+            key: xml::text(upload, "Key").chain_err(|| "Missing Key")?,
+            upload_id: xml::text(upload, "UploadId").chain_err(|| "Missing UploadId")?,
+            initiated: DateTime::parse_from_rfc3339(&initiated)
+                .chain_err(|| format!("Invalid Initiated '{}'", initiated))?
+                .with_timezone(&Utc),
+        });
+    }
+    if xml::element(body, "IsTruncated") != Some("true") {
+        return Ok(None);
+    }
+    match (
+        xml::text(body, "NextKeyMarker"),
+        xml::text(body, "NextUploadIdMarker"),
+    ) {
+        (Some(key), Some(upload_id)) => Ok(Some((key, upload_id))),
+        _ => bail!("Missing NextKeyMarker or NextUploadIdMarker in truncated response"),
+    }
+}
+
+/// Return the uploads initiated more than `age` ago.
+pub(crate) fn stale_uploads(uploads: Vec<Upload>, age: Duration) -> Result<Vec<Upload>> {
+    let age = chrono::Duration::from_std(age).chain_err(|| format!("Invalid age {:?}", age))?;
+    let cutoff = Utc::now() - age;
+    Ok(uploads
+        .into_iter()
+        .filter(|upload| upload.initiated < cutoff)
+        .collect())
+}
+
+/// Return the body of a complete multipart upload request.
+pub(crate) fn complete_body(parts: &[CompletedPart]) -> String {
+    format!(
+        "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+        parts
+            .iter()
+            .map(|p| format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                p.part_number,
+                xml::escape(&p.etag)
+            ))
+            .collect::<String>()
+    )
+}
+
+/// Parse the response to a complete multipart upload request and return the
+/// ETag of the object.
+pub(crate) fn parse_complete_response(body: &str) -> Result<String> {
+    // errors can be returned with a 200 status code after processing started
+    if let Some(error) = xml::element(body, "Error") {
+        return Err(ErrorKind::HttpStatus(200, error.to_string()).into());
+    }
+    xml::text(body, "ETag").chain_err(|| "Missing ETag in response")
+}
+
+/// Parse the text of the first `<tag>` element.
+pub(crate) fn parse_element<T: std::str::FromStr>(xml: &str, tag: &str) -> Result<T> {
+    xml::element(xml, tag)
+        .and_then(|v| v.trim().parse().ok())
+        .chain_err(|| format!("Missing or invalid {}", tag))
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(size: u64) -> MultipartUploadState {
+        MultipartUploadState {
+            bucket: "bucket".to_string(),
+            key: "key".to_string(),
+            upload_id: "id".to_string(),
+            part_size: MIN_PART_SIZE,
+            size,
+            parts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parts() {
+        assert_eq!(1, state(0).num_parts());
+        assert_eq!((0, 0), state(0).part_range(1));
+        let mut s = state(2 * MIN_PART_SIZE + 1);
+        assert_eq!(3, s.num_parts());
+        assert_eq!((2 * MIN_PART_SIZE, 2 * MIN_PART_SIZE + 1), s.part_range(3));
+        assert_eq!(Some(1), s.next_part());
+        s.parts.push(CompletedPart {
+            part_number: 1,
+            etag: "\"a\"".to_string(),
+        });
+        s.parts.push(CompletedPart {
+            part_number: 3,
+            etag: "\"c\"".to_string(),
+        });
+        assert_eq!(Some(2), s.next_part());
+    }
+
+    #[test]
+    fn test_check_part_size() {
+        assert!(check_part_size(0, MIN_PART_SIZE).is_ok());
+        assert!(check_part_size(u64::MAX, MAX_PART_SIZE).is_err());
+        assert!(check_part_size(MAX_PARTS * MIN_PART_SIZE, MIN_PART_SIZE).is_ok());
+        assert!(check_part_size(MAX_PARTS * MIN_PART_SIZE + 1, MIN_PART_SIZE).is_err());
+        assert!(check_part_size(1, 0).is_err());
+        assert!(check_part_size(1, MAX_PART_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn test_multipart_xml() -> Result<()> {
+        let mut parts = Vec::new();
+        let body = "<ListPartsResult><IsTruncated>true</IsTruncated>\
+            <NextPartNumberMarker>1</NextPartNumberMarker>\
+            <Part><PartNumber>1</PartNumber><ETag>&quot;a&quot;</ETag><Size>5</Size></Part>\
+            </ListPartsResult>";
+        assert_eq!(Some("1".to_string()), parse_list_parts(body, &mut parts)?);
+        assert_eq!(
+            vec![Part {
+                part_number: 1,
+                etag: "\"a\"".to_string(),
+                size: 5
+            }],
+            parts
+        );
+        let completed = [CompletedPart {
+            part_number: 1,
+            etag: "\"a\"".to_string(),
+        }];
+        assert_eq!(
+            "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber>\
+            <ETag>&quot;a&quot;</ETag></Part></CompleteMultipartUpload>",
+            complete_body(&completed)
+        );
+        assert!(parse_complete_response("<Error><Code>InternalError</Code></Error>").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_uploads() -> Result<()> {
+        let body = "<ListMultipartUploadsResult><Bucket>bucket</Bucket>\
+            <KeyMarker/><UploadIdMarker/><NextKeyMarker>b</NextKeyMarker>\
+            <NextUploadIdMarker>id2</NextUploadIdMarker><IsTruncated>true</IsTruncated>\
+            <Upload><Key>a</Key><UploadId>id1</UploadId><Initiator><ID>x</ID></Initiator>\
+            <Initiated>2023-01-02T03:04:05.000Z</Initiated></Upload>\
+            <Upload><Key>b</Key><UploadId>id2</UploadId>\
+            <Initiated>2099-01-02T03:04:05.000Z</Initiated></Upload>\
+            </ListMultipartUploadsResult>";
+        let mut uploads = Vec::new();
+        assert_eq!(
+            Some(("b".to_string(), "id2".to_string())),
+            parse_list_uploads(body, &mut uploads)?
+        );
+        assert_eq!(2, uploads.len());
+        assert_eq!("id1", uploads[0].upload_id);
+        let stale = stale_uploads(uploads, Duration::from_secs(24 * 3600))?;
+        assert_eq!(1, stale.len());
+        assert_eq!("a", stale[0].key);
+        let config = Config::new(
+            Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?,
+            "access",
+            "secret",
+            crate::Region::UsEast1,
+        );
+        assert_eq!(
+            "https://play.min.io/bucket/?uploads&prefix=p&key-marker=b&upload-id-marker=id2",
+            config
+                .list_uploads_url("bucket", "p", Some(&("b".to_string(), "id2".to_string())))?
+                .as_str()
+        );
+        Ok(())
+    }
+}
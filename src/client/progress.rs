@@ -0,0 +1,112 @@
+//! Transfer progress reporting.
+
+use std::io::{Read, Write};
+
+// -----------------------------------------------------------------------------
+/// Event reported to progress observers during a transfer.
+///
+/// Observers are plain `FnMut(TransferEvent)` closures, invoked on the thread
+/// which started the transfer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransferEvent {
+    /// The transfer started, `total` is the number of bytes to transfer, when known.
+    Started { total: Option<u64> },
+    /// `bytes` more bytes were sent or received.
+    ///
+    /// Parts of multipart uploads and chunks of parallel downloads, which are
+    /// retried on failure, are reported once transferred, so that the bytes of
+    /// failed attempts are not counted.
+    BytesTransferred { bytes: u64 },
+    /// A part of a multipart upload or a chunk of a parallel download was
+    /// transferred; parts are numbered from 1.
+    PartCompleted { part_number: u32, size: u64 },
+    /// Transferring a part failed with `error` and is being retried;
+    /// `attempt` is the number of the retry, starting from 1.
+    Retry {
+        part_number: u32,
+        attempt: u32,
+        error: String,
+    },
+    /// The transfer completed successfully.
+    Completed,
+}
+
+// -----------------------------------------------------------------------------
+/// Writer reporting the bytes written to the inner writer.
+pub(crate) struct ProgressWriter<W, F> {
+    inner: W,
+    progress: F,
+}
+
+impl<W: Write, F: FnMut(TransferEvent)> ProgressWriter<W, F> {
+    pub(crate) fn new(inner: W, progress: F) -> Self {
+        ProgressWriter { inner, progress }
+    }
+}
+
+impl<W: Write, F: FnMut(TransferEvent)> Write for ProgressWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if n > 0 {
+            (self.progress)(TransferEvent::BytesTransferred { bytes: n as u64 });
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Reader reporting the bytes read from the inner reader.
+pub(crate) struct ProgressReader<R, F> {
+    inner: R,
+    progress: F,
+}
+
+impl<R: Read, F: FnMut(TransferEvent)> ProgressReader<R, F> {
+    pub(crate) fn new(inner: R, progress: F) -> Self {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read, F: FnMut(TransferEvent)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            (self.progress)(TransferEvent::BytesTransferred { bytes: n as u64 });
+        }
+        Ok(n)
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_reader_writer() -> std::io::Result<()> {
+        let mut events = Vec::new();
+        let data = [7_u8; 100];
+        let mut out = [0_u8; 100];
+        {
+            let mut reader = ProgressReader::new(&data[..], |e| events.push(e));
+            reader.read_exact(&mut out)?;
+        }
+        let mut writer = ProgressWriter::new(Vec::new(), |e| events.push(e));
+        writer.write_all(&out[..60])?;
+        writer.write_all(&out[60..])?;
+        assert_eq!(
+            vec![
+                TransferEvent::BytesTransferred { bytes: 100 },
+                TransferEvent::BytesTransferred { bytes: 60 },
+                TransferEvent::BytesTransferred { bytes: 40 },
+            ],
+            events
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+//! `SelectObjectContent` requests, available with the `event-stream` feature.
+
+use super::{Client, HttpBody};
+use crate::errors::*;
+use crate::{Addressing, Method, SelectEvents, SelectRequest};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+impl Client {
+    /// Run an S3 Select query on an object and return the events of the response.
+    ///
+    /// ```no_run
+    /// # fn main() -> s3v4::Result<()> {
+    /// # let endpoint = url::Url::parse("https://play.min.io").unwrap();
+    /// # let client = s3v4::client::Client::new(endpoint, "access", "secret", s3v4::Region::UsEast1);
+    /// let request = s3v4::SelectRequest::new("SELECT * FROM S3Object s WHERE s._1 = 'x'");
+    /// let mut records = Vec::new();
+    /// client
+    ///     .select_object_content("bucket", "data.csv", &request)?
+    ///     .read_records(&mut records)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_object_content(
+        &self,
+        bucket: &str,
+        key: &str,
+        request: &SelectRequest,
+    ) -> Result<SelectEvents<Box<dyn Read + Send>>> {
+        let url = request.url(&self.config.endpoint, bucket, key, Addressing::Path)?;
+        let xml = request.body()?;
+        let body = HttpBody {
+            len: xml.len() as u64,
+            reader: Box::new(xml.as_bytes()),
+        };
+        let response = self.send_with_payload_hash(
+            &Method::Post,
+            &url,
+            &BTreeMap::new(),
+            Some(body),
+            &request.payload_hash()?,
+        )?;
+        Ok(SelectEvents::new(response.body))
+    }
+}
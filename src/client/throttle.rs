@@ -0,0 +1,166 @@
+//! Bandwidth throttling.
+
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// -----------------------------------------------------------------------------
+/// Token bucket rate limiter, in bytes per second.
+///
+/// Clones share the same bucket: a limiter attached to several clients, or to
+/// a client used by several threads, limits the total bandwidth of all the
+/// transfers. The rate can be changed at any time with [RateLimiter::set_rate],
+/// also while transfers are running.
+///
+/// ```
+/// use s3v4::client::RateLimiter;
+/// let limiter = RateLimiter::new(1024 * 1024);
+/// // later, e.g. outside office hours
+/// limiter.set_rate(0);
+/// assert_eq!(0, limiter.rate());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: u64,
+    /// Available bytes, negative when transfers are ahead of the rate.
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `bytes_per_second` bytes per second with bursts
+    /// of up to one second of data; a rate of zero disables limiting.
+    pub fn new(bytes_per_second: u64) -> Self {
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: bytes_per_second,
+                tokens: bytes_per_second as f64,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// Return the current rate in bytes per second, zero if unlimited.
+    pub fn rate(&self) -> u64 {
+        self.lock().rate
+    }
+
+    /// Change the rate, zero disables limiting.
+    pub fn set_rate(&self, bytes_per_second: u64) {
+        let mut bucket = self.lock();
+        bucket.refill();
+        bucket.rate = bytes_per_second;
+        bucket.tokens = bucket.tokens.min(bytes_per_second as f64);
+    }
+
+    /// Take `bytes` from the bucket and return how long to wait before
+    /// transferring them.
+    pub(crate) fn reserve(&self, bytes: u64) -> Duration {
+        let mut bucket = self.lock();
+        if bucket.rate == 0 {
+            return Duration::ZERO;
+        }
+        bucket.refill();
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / bucket.rate as f64)
+        }
+    }
+
+    /// Block the current thread until `bytes` can be transferred.
+    pub(crate) fn wait(&self, bytes: u64) {
+        let delay = self.reserve(bytes);
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Wait until `bytes` can be transferred without blocking the executor.
+    #[cfg(feature = "async")]
+    pub(crate) async fn wait_async(&self, bytes: u64) {
+        let delay = self.reserve(bytes);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        // the bucket is always left in a consistent state
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Bucket {
+    /// Add the tokens accumulated since the last refill, up to one second of data.
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Reader or writer waiting for the optional limiter after each read or write.
+pub(crate) struct Throttled<T> {
+    inner: T,
+    limiter: Option<RateLimiter>,
+}
+
+impl<T> Throttled<T> {
+    pub(crate) fn new(inner: T, limiter: Option<RateLimiter>) -> Self {
+        Throttled { inner, limiter }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(limiter) = &self.limiter {
+            limiter.wait(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Throttled<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(limiter) = &self.limiter {
+            limiter.wait(n as u64);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(1000);
+        // the initial burst is available immediately
+        assert!(limiter.reserve(1000).is_zero());
+        let delay = limiter.reserve(500);
+        assert!(delay > Duration::from_millis(400) && delay <= Duration::from_millis(500));
+        // a lower rate does not restore tokens, the debt is paid at the new rate
+        limiter.set_rate(100);
+        assert!(limiter.reserve(100) > Duration::from_secs(5));
+        limiter.set_rate(0);
+        assert!(limiter.reserve(1_000_000).is_zero());
+    }
+}
@@ -0,0 +1,137 @@
+//! HTTP transports used by the blocking client.
+//!
+//! [Client](super::Client) signs requests and hands them to an [HttpTransport],
+//! which only has to send them: implement the trait to use the client with the
+//! HTTP stack already used by an application. Implementations are provided for
+//! `ureq::Agent`, the default, and with the `reqwest` feature for
+//! `reqwest::blocking::Client`.
+
+use crate::errors::*;
+use crate::Method;
+use std::io::Read;
+use url::Url;
+
+// -----------------------------------------------------------------------------
+/// Signed request to send.
+pub struct HttpRequest<'a> {
+    pub method: Method,
+    pub url: Url,
+    /// Headers to send, including the signature headers.
+    pub headers: Vec<(String, String)>,
+    /// Request body, `None` for requests without body.
+    pub body: Option<HttpBody<'a>>,
+}
+
+/// Body of a request.
+pub struct HttpBody<'a> {
+    pub reader: Box<dyn Read + 'a>,
+    /// Length of the body, to be sent in the `Content-Length` header.
+    pub len: u64,
+}
+
+// -----------------------------------------------------------------------------
+/// Response received by a transport.
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl HttpResponse {
+    /// Return the value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Read the whole body as text.
+    pub fn into_string(mut self) -> Result<String> {
+        let mut body = String::new();
+        self.body
+            .read_to_string(&mut body)
+            .chain_err(|| ErrorKind::Transport("Error reading response".to_string()))?;
+        Ok(body)
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Blocking HTTP transport.
+pub trait HttpTransport: Send + Sync {
+    /// Send a request and return the response.
+    ///
+    /// Responses with error status codes must be returned as responses, not as
+    /// errors: the client converts them into [ErrorKind::HttpStatus] errors.
+    /// Failures to send the request or receive the response should be returned
+    /// as [ErrorKind::Transport] errors, which transfers retry.
+    fn execute(&self, request: HttpRequest<'_>) -> Result<HttpResponse>;
+}
+
+impl HttpTransport for ureq::Agent {
+    fn execute(&self, request: HttpRequest<'_>) -> Result<HttpResponse> {
+        let mut r = self.request_url(request.method.as_str(), &request.url);
+        for (k, v) in &request.headers {
+            r = r.set(k, v);
+        }
+        let result = match request.body {
+            Some(body) => r
+                .set("content-length", &body.len.to_string())
+                .send(body.reader),
+            None => r.call(),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(err) => {
+                return Err(err)
+                    .chain_err(|| ErrorKind::Transport("HTTP request failed".to_string()))
+            }
+        };
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|k| response.header(&k).map(|v| (k.clone(), v.to_string())))
+            .collect();
+        Ok(HttpResponse {
+            status: response.status(),
+            headers,
+            body: Box::new(response.into_reader()),
+        })
+    }
+}
+
+/// The body of requests is buffered in memory before being sent, since `reqwest`
+/// requires `'static` readers: progress reporting and rate limiting apply to
+/// the buffering of the body.
+#[cfg(feature = "reqwest")]
+impl HttpTransport for reqwest::blocking::Client {
+    fn execute(&self, request: HttpRequest<'_>) -> Result<HttpResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_str().as_bytes())
+            .chain_err(|| format!("Invalid method {}", request.method))?;
+        let mut r = self.request(method, request.url);
+        for (k, v) in request.headers {
+            r = r.header(k, v);
+        }
+        if let Some(mut body) = request.body {
+            let mut data = Vec::with_capacity(body.len as usize);
+            body.reader
+                .read_to_end(&mut data)
+                .chain_err(|| "Error reading request body")?;
+            r = r.body(data);
+        }
+        let response = r
+            .send()
+            .chain_err(|| ErrorKind::Transport("HTTP request failed".to_string()))?;
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+            .collect();
+        Ok(HttpResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: Box::new(response),
+        })
+    }
+}
@@ -0,0 +1,257 @@
+//! Downloads verified against the checksums returned by the server.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/userguide/checking-object-integrity.html)
+
+use super::attributes::{head_headers, parse_head_response};
+use super::throttle::Throttled;
+use super::{Client, ObjectInfo};
+use crate::crypto::{Backend, Crypto, Sha256Context};
+use crate::errors::*;
+use crate::{Method, SSE_CUSTOMER_ALGORITHM_HEADER, SSE_HEADER};
+use base64::Engine;
+use md5::{Digest, Md5};
+use std::io::{Read, Write};
+
+// -----------------------------------------------------------------------------
+/// Algorithm used to verify a download.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// `x-amz-checksum-sha256` header.
+    Sha256,
+    /// `x-amz-checksum-crc32c` header.
+    Crc32c,
+    /// MD5 digest in the ETag of objects neither uploaded with a multipart
+    /// upload nor encrypted with SSE-KMS or SSE-C.
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "SHA256",
+            ChecksumAlgorithm::Crc32c => "CRC32C",
+            ChecksumAlgorithm::Md5 => "MD5",
+        }
+    }
+}
+
+impl std::fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Object downloaded by [Client::get_object_verified].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedObject {
+    /// Metadata returned with the object.
+    pub info: ObjectInfo,
+    /// Algorithm of the verified checksum.
+    pub algorithm: ChecksumAlgorithm,
+}
+
+impl Client {
+    /// Download an object, writing its content to `writer`, and verify it
+    /// against the checksum returned by the server, computed while streaming.
+    ///
+    /// The SHA-256 or CRC32C checksum of the object is used if available,
+    /// otherwise the MD5 digest in the ETag. An [ErrorKind::ChecksumMismatch]
+    /// error is returned if the computed checksum differs, and an error is
+    /// returned before downloading if the object has no checksum which can be
+    /// verified, e.g. objects uploaded with a multipart upload without a full
+    /// object checksum; on error the data written to `writer` must be discarded.
+    pub fn get_object_verified(
+        &self,
+        bucket: &str,
+        key: &str,
+        writer: &mut impl Write,
+    ) -> Result<VerifiedObject> {
+        let url = self.object_url(bucket, key)?;
+        let response = self.send(&Method::Get, &url, &head_headers(), None)?;
+        let (algorithm, expected) = expected_checksum(&response.headers)?;
+        let info = parse_head_response(&response.headers)?;
+        let mut reader = HashingReader {
+            inner: response.body,
+            hasher: Hasher::new(algorithm),
+        };
+        std::io::copy(
+            &mut reader,
+            &mut Throttled::new(writer, self.limiter.clone()),
+        )
+        .chain_err(|| "Error reading object")?;
+        let actual = reader.hasher.finish();
+        if actual != expected {
+            return Err(
+                ErrorKind::ChecksumMismatch(algorithm.to_string(), expected, actual).into(),
+            );
+        }
+        Ok(VerifiedObject { info, algorithm })
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Return the algorithm and value of the checksum to verify, encoded as in the
+/// response headers: base64 for checksums and hex for the ETag.
+fn expected_checksum(headers: &[(String, String)]) -> Result<(ChecksumAlgorithm, String)> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+    };
+    // composite checksums of multipart uploads end with `-<number of parts>`
+    let full_object = |value: &&str| !value.contains('-');
+    if let Some(sha256) = header("x-amz-checksum-sha256").filter(full_object) {
+        return Ok((ChecksumAlgorithm::Sha256, sha256.to_string()));
+    }
+    if let Some(crc32c) = header("x-amz-checksum-crc32c").filter(full_object) {
+        return Ok((ChecksumAlgorithm::Crc32c, crc32c.to_string()));
+    }
+    let etag = header("etag")
+        .chain_err(|| "Missing ETag header")?
+        .trim_matches('"');
+    let encrypted = header(SSE_CUSTOMER_ALGORITHM_HEADER).is_some()
+        || header(SSE_HEADER).map_or(false, |sse| sse.starts_with("aws:kms"));
+    if etag.len() != 32 || !etag.chars().all(|c| c.is_ascii_hexdigit()) || encrypted {
+        bail!(
+            "No checksum to verify: the ETag '{}' is not the MD5 digest of the object",
+            etag
+        );
+    }
+    Ok((ChecksumAlgorithm::Md5, etag.to_ascii_lowercase()))
+}
+
+/// Checksum computation.
+enum Hasher {
+    Sha256(<Crypto as Backend>::Sha256),
+    Crc32c(u32),
+    Md5(Md5),
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Crypto::sha256_context()),
+            ChecksumAlgorithm::Crc32c => Hasher::Crc32c(0),
+            ChecksumAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(context) => Sha256Context::update(context, data),
+            Hasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            Hasher::Md5(md5) => md5.update(data),
+        }
+    }
+
+    /// Return the checksum encoded as by [expected_checksum].
+    fn finish(self) -> String {
+        let base64 = |data: &[u8]| base64::engine::general_purpose::STANDARD.encode(data);
+        match self {
+            Hasher::Sha256(context) => base64(&Sha256Context::finish(context)),
+            Hasher::Crc32c(crc) => base64(&crc.to_be_bytes()),
+            Hasher::Md5(md5) => hex::encode(md5.finalize()),
+        }
+    }
+}
+
+/// Reader computing the checksum of the data read.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn checksum(algorithm: ChecksumAlgorithm, data: &[u8]) -> String {
+        let mut hasher = Hasher::new(algorithm);
+        for chunk in data.chunks(3) {
+            hasher.update(chunk);
+        }
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_checksums() {
+        use ChecksumAlgorithm::*;
+        // CRC32C check value
+        assert_eq!("4waSgw==", checksum(Crc32c, b"123456789"));
+        assert_eq!(
+            "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=",
+            checksum(Sha256, b"")
+        );
+        assert_eq!(
+            "5eb63bbbe01eeed093cb22bb8f5acdc3",
+            checksum(Md5, b"hello world")
+        );
+    }
+
+    #[test]
+    fn test_expected_checksum() -> Result<()> {
+        let etag = "\"5EB63BBBE01EEED093CB22BB8F5ACDC3\"";
+        assert_eq!(
+            (
+                ChecksumAlgorithm::Sha256,
+                "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=".to_string()
+            ),
+            expected_checksum(&headers(&[
+                ("ETag", etag),
+                ("x-amz-checksum-crc32c", "4waSgw=="),
+                (
+                    "x-amz-checksum-sha256",
+                    "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="
+                ),
+            ]))?
+        );
+        assert_eq!(
+            (ChecksumAlgorithm::Crc32c, "4waSgw==".to_string()),
+            expected_checksum(&headers(&[
+                ("ETag", etag),
+                ("x-amz-checksum-crc32c", "4waSgw=="),
+            ]))?
+        );
+        // composite checksum
+        assert_eq!(
+            (
+                ChecksumAlgorithm::Md5,
+                "5eb63bbbe01eeed093cb22bb8f5acdc3".to_string()
+            ),
+            expected_checksum(&headers(&[
+                ("ETag", etag),
+                ("x-amz-checksum-crc32c", "4waSgw==-2"),
+            ]))?
+        );
+        assert!(expected_checksum(&headers(&[(
+            "ETag",
+            "\"9b2cf535f27731c974343645a3985328-2\""
+        )]))
+        .is_err());
+        assert!(expected_checksum(&headers(&[("ETag", etag), (SSE_HEADER, "aws:kms")])).is_err());
+        assert!(expected_checksum(&headers(&[
+            ("ETag", etag),
+            (SSE_CUSTOMER_ALGORITHM_HEADER, "AES256")
+        ]))
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+//! Credentials providers and signing with refreshable credentials.
+
+use crate::errors::*;
+use crate::{pre_signed_url_with_query, signature_with_options, Method, Region, SigningOptions};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Header containing the session token of temporary credentials.
+pub const SECURITY_TOKEN_HEADER: &str = "x-amz-security-token";
+/// Query parameter containing the session token in pre-signed URLs.
+const SECURITY_TOKEN_PARAM: &str = "X-Amz-Security-Token";
+
+// -----------------------------------------------------------------------------
+/// Access key, secret key and, for temporary credentials, session token and
+/// expiration time.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub access: String,
+    pub secret: String,
+    /// Session token of temporary credentials, e.g. returned by STS.
+    pub session_token: Option<String>,
+    /// Expiration time, `None` if the credentials do not expire.
+    pub expiry: Option<DateTime<Utc>>,
+}
+
+impl Credentials {
+    /// Create long-term credentials.
+    pub fn new(access: &str, secret: &str) -> Self {
+        Credentials {
+            access: access.to_string(),
+            secret: secret.to_string(),
+            session_token: None,
+            expiry: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    /// Print the access key only.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access", &self.access)
+            .field("expiry", &self.expiry)
+            .finish_non_exhaustive()
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Source of credentials, e.g. the instance metadata service, Vault or STS.
+///
+/// Implemented for [Credentials], which are returned as they are, and for
+/// closures returning credentials.
+pub trait ProvideCredentials: Send + Sync {
+    /// Return the current credentials; called again when the returned ones
+    /// are about to expire.
+    fn provide_credentials(&self) -> Result<Credentials>;
+}
+
+impl ProvideCredentials for Credentials {
+    fn provide_credentials(&self) -> Result<Credentials> {
+        Ok(self.clone())
+    }
+}
+
+impl<F> ProvideCredentials for F
+where
+    F: Fn() -> Result<Credentials> + Send + Sync,
+{
+    fn provide_credentials(&self) -> Result<Credentials> {
+        self()
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Signer retrieving the credentials from a [ProvideCredentials] for each
+/// request, so that rotated credentials are used as soon as they are available.
+///
+/// Credentials are cached and requested again `refresh_margin` seconds before
+/// they expire (five minutes by default); credentials without expiration time
+/// are requested only once, or after [Signer::invalidate]. Session tokens are
+/// signed and sent in the `x-amz-security-token` header, or in the
+/// `X-Amz-Security-Token` parameter of pre-signed URLs.
+///
+/// Clones share the same provider and cached credentials.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// use s3v4::{Credentials, Signer};
+/// let signer = Signer::new(
+///     || Ok(Credentials::new("access", "secret")),
+///     s3v4::Region::UsEast1,
+///     "s3",
+/// );
+/// let url = url::Url::parse("https://play.min.io/bucket/key").unwrap();
+/// let headers = signer.sign(&s3v4::Method::Get, &url, &Default::default(), "UNSIGNED-PAYLOAD")?;
+/// assert_eq!("authorization", headers[2].0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Signer {
+    provider: Arc<dyn ProvideCredentials>,
+    region: Region,
+    service: String,
+    options: SigningOptions,
+    refresh_margin: u64,
+    cached: Arc<Mutex<Option<Credentials>>>,
+}
+
+impl Signer {
+    /// Create a signer for `service` (e.g. "s3") in `region`.
+    pub fn new(provider: impl ProvideCredentials + 'static, region: Region, service: &str) -> Self {
+        Signer {
+            provider: Arc::new(provider),
+            region,
+            service: service.to_string(),
+            options: SigningOptions::default(),
+            refresh_margin: 300,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sign requests with the specified options; headers in `options.headers`
+    /// are signed with those passed to [Signer::sign].
+    pub fn with_options(mut self, options: SigningOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Request new credentials `seconds` seconds before the current ones expire.
+    pub fn with_refresh_margin(mut self, seconds: u64) -> Self {
+        self.refresh_margin = seconds;
+        self
+    }
+
+    /// Return the region of the signed requests.
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
+
+    /// Return the cached credentials, requesting them from the provider if
+    /// missing or about to expire.
+    ///
+    /// The provider is called while holding a lock, so that concurrent
+    /// requests do not all request new credentials.
+    pub fn credentials(&self) -> Result<Credentials> {
+        let mut cached = self.cached.lock().unwrap_or_else(|e| e.into_inner());
+        let margin = Duration::seconds(self.refresh_margin as i64);
+        let valid = |credentials: &Credentials| match credentials.expiry {
+            Some(expiry) => expiry - margin > Utc::now(),
+            None => true,
+        };
+        match cached.as_ref() {
+            Some(credentials) if valid(credentials) => Ok(credentials.clone()),
+            _ => {
+                let credentials = self
+                    .provider
+                    .provide_credentials()
+                    .chain_err(|| "Error retrieving credentials")?;
+                // credentials within the refresh margin are used, and requested
+                // again for the next request
+                if matches!(credentials.expiry, Some(expiry) if expiry <= Utc::now()) {
+                    bail!(
+                        "Credentials provided for '{}' are expired",
+                        credentials.access
+                    );
+                }
+                *cached = Some(credentials.clone());
+                Ok(credentials)
+            }
+        }
+    }
+
+    /// Discard the cached credentials, e.g. after they were revoked: the next
+    /// request retrieves them from the provider.
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Sign a request and return the headers to send: the signature headers
+    /// followed by `headers`, which are signed as well. `payload_hash` is
+    /// usually `UNSIGNED-PAYLOAD`.
+    pub fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &BTreeMap<String, String>,
+        payload_hash: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let credentials = self.credentials()?;
+        let mut options = self.options.clone();
+        options.headers.extend(headers.clone());
+        if let Some(token) = &credentials.session_token {
+            options
+                .headers
+                .insert(SECURITY_TOKEN_HEADER.to_string(), token.clone());
+        }
+        let signature = signature_with_options(
+            url,
+            method,
+            &credentials.access,
+            &credentials.secret,
+            &self.region,
+            &self.service,
+            payload_hash,
+            &options,
+        )?;
+        let mut signed = vec![
+            ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+            (options.date_header.name().to_string(), signature.date_time),
+            ("authorization".to_string(), signature.auth_header),
+        ];
+        signed.extend(options.headers);
+        Ok(signed)
+    }
+
+    /// Return a URL valid for `expiration` seconds, or until the credentials
+    /// expire if earlier.
+    pub fn presign(&self, method: &Method, url: &Url, expiration: u64) -> Result<String> {
+        let credentials = self.credentials()?;
+        let query = match &credentials.session_token {
+            Some(token) => vec![(SECURITY_TOKEN_PARAM, token.as_str())],
+            None => Vec::new(),
+        };
+        pre_signed_url_with_query(
+            &credentials.access,
+            &credentials.secret,
+            expiration,
+            url,
+            method,
+            "UNSIGNED-PAYLOAD",
+            &self.region,
+            &Utc::now(),
+            &self.service,
+            &query,
+            &self.options,
+        )
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_signer_refreshes_credentials() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider_calls = calls.clone();
+        let signer = Signer::new(
+            move || {
+                let n = provider_calls.fetch_add(1, Ordering::SeqCst);
+                Ok(Credentials {
+                    access: format!("access{}", n),
+                    secret: "secret".to_string(),
+                    session_token: Some(format!("token{}", n)),
+                    expiry: Some(Utc::now() + Duration::seconds(if n == 0 { 60 } else { 3600 })),
+                })
+            },
+            Region::UsEast1,
+            "s3",
+        );
+        let url = Url::parse("https://play.min.io/bucket/key").chain_err(|| "Error parsing url")?;
+        let headers = signer.sign(&Method::Get, &url, &BTreeMap::new(), "UNSIGNED-PAYLOAD")?;
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert!(headers[2].1.contains("Credential=access0/"));
+        // the first credentials expire within the refresh margin
+        let headers = signer.sign(&Method::Get, &url, &BTreeMap::new(), "UNSIGNED-PAYLOAD")?;
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert!(header("authorization")
+            .unwrap()
+            .contains("Credential=access1/"));
+        assert!(header("authorization")
+            .unwrap()
+            .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"));
+        assert_eq!(Some("token1".to_string()), header(SECURITY_TOKEN_HEADER));
+        // cached
+        let presigned = signer.presign(&Method::Get, &url, 3600)?;
+        assert!(presigned.contains("X-Amz-Security-Token=token1&"));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        signer.clone().invalidate();
+        assert_eq!("access2", signer.credentials()?.access);
+        // no refresh margin: the first credentials are used
+        calls.store(0, Ordering::SeqCst);
+        let signer = signer.with_refresh_margin(0);
+        signer.invalidate();
+        assert_eq!("access0", signer.credentials()?.access);
+        Ok(())
+    }
+
+    #[test]
+    fn test_static_credentials() -> Result<()> {
+        let signer = Signer::new(Credentials::new("access", "secret"), Region::UsEast1, "s3");
+        let url = Url::parse("https://play.min.io/bucket/key").chain_err(|| "Error parsing url")?;
+        let mut headers = BTreeMap::new();
+        headers.insert("x-amz-meta-a".to_string(), "1".to_string());
+        let signed = signer.sign(&Method::Put, &url, &headers, "UNSIGNED-PAYLOAD")?;
+        assert_eq!(4, signed.len());
+        assert_eq!(("x-amz-meta-a".to_string(), "1".to_string()), signed[3]);
+        assert!(!signer
+            .presign(&Method::Get, &url, 60)?
+            .contains("Security-Token"));
+        assert!(!format!("{:?}", signer.credentials()?).contains("secret"));
+        // expired credentials are rejected
+        let expired = Credentials {
+            expiry: Some(Utc::now()),
+            ..Credentials::new("access", "secret")
+        };
+        assert!(Signer::new(expired, Region::UsEast1, "s3")
+            .credentials()
+            .is_err());
+        Ok(())
+    }
+}
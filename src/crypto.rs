@@ -0,0 +1,185 @@
+//! Hashing and HMAC backends.
+//!
+//! The backend is selected at compile time through cargo features:
+//! * `rust-crypto` (default): pure Rust `sha2` and `hmac` crates
+//! * `ring`: the `ring` crate
+//! * `aws-lc-rs`: the `aws-lc-rs` crate; enable its `fips` feature to use the
+//!   FIPS-validated build of AWS-LC
+//!
+//! When more than one backend is enabled `aws-lc-rs` takes precedence over `ring`,
+//! which takes precedence over `rust-crypto`.
+
+#[cfg(not(any(feature = "rust-crypto", feature = "ring", feature = "aws-lc-rs")))]
+compile_error!("one of the `rust-crypto`, `ring` or `aws-lc-rs` features must be enabled");
+
+/// Hashing and HMAC functions required by the signing algorithm.
+pub(crate) trait Backend {
+    /// Incremental SHA-256 computation.
+    type Sha256: Sha256Context;
+    /// Return the SHA-256 digest of `data`.
+    fn sha256(data: &[u8]) -> [u8; 32];
+    /// Return the HMAC-SHA256 of `data` computed with `key`.
+    fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32];
+    /// Return a context computing the SHA-256 digest of data received in chunks,
+    /// e.g. while streaming a download.
+    #[cfg_attr(not(feature = "client"), allow(dead_code))]
+    fn sha256_context() -> Self::Sha256;
+}
+
+/// SHA-256 digest of data received in chunks.
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) trait Sha256Context {
+    fn update(&mut self, data: &[u8]);
+    fn finish(self) -> [u8; 32];
+}
+
+#[cfg(feature = "aws-lc-rs")]
+pub(crate) type Crypto = AwsLc;
+#[cfg(all(feature = "ring", not(feature = "aws-lc-rs")))]
+pub(crate) type Crypto = Ring;
+#[cfg(all(
+    feature = "rust-crypto",
+    not(any(feature = "ring", feature = "aws-lc-rs"))
+))]
+pub(crate) type Crypto = RustCrypto;
+
+// -----------------------------------------------------------------------------
+/// `sha2` and `hmac` backend.
+#[cfg(feature = "rust-crypto")]
+#[allow(dead_code)]
+pub(crate) struct RustCrypto;
+
+#[cfg(feature = "rust-crypto")]
+impl Backend for RustCrypto {
+    type Sha256 = sha2::Sha256;
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        use sha2::Digest;
+        sha2::Sha256::digest(data).into()
+    }
+    fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+        use hmac::Mac;
+        // HMAC accepts keys of any length
+        let mut hmac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key)
+            .expect("HMAC can take keys of any size");
+        hmac.update(data);
+        hmac.finalize().into_bytes().into()
+    }
+    fn sha256_context() -> Self::Sha256 {
+        use sha2::Digest;
+        sha2::Sha256::new()
+    }
+}
+
+#[cfg(feature = "rust-crypto")]
+impl Sha256Context for sha2::Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(self, data);
+    }
+    fn finish(self) -> [u8; 32] {
+        sha2::Digest::finalize(self).into()
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// `ring` backend.
+#[cfg(feature = "ring")]
+#[allow(dead_code)]
+pub(crate) struct Ring;
+
+#[cfg(feature = "ring")]
+impl Backend for Ring {
+    type Sha256 = ring::digest::Context;
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        to_array(ring::digest::digest(&ring::digest::SHA256, data).as_ref())
+    }
+    fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key);
+        to_array(ring::hmac::sign(&key, data).as_ref())
+    }
+    fn sha256_context() -> Self::Sha256 {
+        ring::digest::Context::new(&ring::digest::SHA256)
+    }
+}
+
+#[cfg(feature = "ring")]
+impl Sha256Context for ring::digest::Context {
+    fn update(&mut self, data: &[u8]) {
+        ring::digest::Context::update(self, data);
+    }
+    fn finish(self) -> [u8; 32] {
+        to_array(ring::digest::Context::finish(self).as_ref())
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// `aws-lc-rs` backend.
+#[cfg(feature = "aws-lc-rs")]
+pub(crate) struct AwsLc;
+
+#[cfg(feature = "aws-lc-rs")]
+impl Backend for AwsLc {
+    type Sha256 = aws_lc_rs::digest::Context;
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        to_array(aws_lc_rs::digest::digest(&aws_lc_rs::digest::SHA256, data).as_ref())
+    }
+    fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+        let key = aws_lc_rs::hmac::Key::new(aws_lc_rs::hmac::HMAC_SHA256, key);
+        to_array(aws_lc_rs::hmac::sign(&key, data).as_ref())
+    }
+    fn sha256_context() -> Self::Sha256 {
+        aws_lc_rs::digest::Context::new(&aws_lc_rs::digest::SHA256)
+    }
+}
+
+#[cfg(feature = "aws-lc-rs")]
+impl Sha256Context for aws_lc_rs::digest::Context {
+    fn update(&mut self, data: &[u8]) {
+        aws_lc_rs::digest::Context::update(self, data);
+    }
+    fn finish(self) -> [u8; 32] {
+        to_array(aws_lc_rs::digest::Context::finish(self).as_ref())
+    }
+}
+
+#[cfg(any(feature = "ring", feature = "aws-lc-rs"))]
+fn to_array(digest: &[u8]) -> [u8; 32] {
+    let mut out = [0_u8; 32];
+    out.copy_from_slice(digest);
+    out
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4231, test case 2
+    const HMAC_KEY: &[u8] = b"Jefe";
+    const HMAC_DATA: &[u8] = b"what do ya want for nothing?";
+    const HMAC_EXPECTED: &str = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+    const SHA256_EXPECTED: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn check<B: Backend>() {
+        assert_eq!(SHA256_EXPECTED, hex::encode(B::sha256(b"")));
+        let mut context = B::sha256_context();
+        context.update(b"what do ya ");
+        context.update(b"want for nothing?");
+        assert_eq!(B::sha256(HMAC_DATA), context.finish());
+        assert_eq!(
+            HMAC_EXPECTED,
+            hex::encode(B::hmac_sha256(HMAC_KEY, HMAC_DATA))
+        );
+    }
+
+    #[test]
+    fn test_backends() {
+        #[cfg(feature = "rust-crypto")]
+        check::<RustCrypto>();
+        #[cfg(feature = "ring")]
+        check::<Ring>();
+        #[cfg(feature = "aws-lc-rs")]
+        check::<AwsLc>();
+    }
+}
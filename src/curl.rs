@@ -0,0 +1,166 @@
+//! `curl` command lines reproducing signed requests, e.g. to report signature
+//! errors to the support of S3-compatible servers.
+
+use crate::errors::*;
+use crate::{
+    host_header, lower_case_headers, signature_with_options, Method, Region, SigningOptions,
+};
+use url::Url;
+
+// -----------------------------------------------------------------------------
+/// Sign a request like [signature_with_options] and return a `curl` command
+/// line sending it with all the signed headers.
+///
+/// The body of the request, if any, is read from the file at `body`, which
+/// must match `payload_hash` unless it is `UNSIGNED-PAYLOAD`. The `host`
+/// header is only added when the signed value differs from the one sent by
+/// `curl`, e.g. with [SigningOptions::host_override]. The command is valid
+/// for the time S3 accepts the signature, usually fifteen minutes.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// let url = url::Url::parse("https://play.min.io/bucket/key").unwrap();
+/// let command = s3v4::curl_command(
+///     &url,
+///     &s3v4::Method::Put,
+///     "access",
+///     "secret",
+///     &s3v4::Region::UsEast1,
+///     "s3",
+///     "UNSIGNED-PAYLOAD",
+///     &s3v4::SigningOptions::default(),
+///     Some("data.bin"),
+/// )?;
+/// assert!(command.starts_with("curl -X PUT --upload-file data.bin "));
+/// # Ok(())
+/// # }
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn curl_command(
+    url: &Url,
+    method: &Method,
+    access: &str,
+    secret: &str,
+    region: &Region,
+    service: &str,
+    payload_hash: &str,
+    options: &SigningOptions,
+    body: Option<&str>,
+) -> Result<String> {
+    let signature = signature_with_options(
+        url,
+        method,
+        access,
+        secret,
+        region,
+        service,
+        payload_hash,
+        options,
+    )?;
+    let mut args = vec!["curl".to_string()];
+    match method {
+        // `-X HEAD` waits for a body
+        Method::Head => args.push("--head".to_string()),
+        _ => args.extend(["-X".to_string(), method.as_str().to_string()]),
+    }
+    if let Some(body) = body {
+        args.extend(["--upload-file".to_string(), quote(body)]);
+    }
+    // `curl` sends the host and the port only if it is in the URL
+    let host = host_header(url, options)?;
+    let sent_host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut headers = Vec::new();
+    if host != sent_host {
+        headers.push(("host".to_string(), host));
+    }
+    headers.push(("x-amz-content-sha256".to_string(), payload_hash.to_string()));
+    headers.push((options.date_header.name().to_string(), signature.date_time));
+    headers.push(("authorization".to_string(), signature.auth_header));
+    // `host` and the signature headers replace those in the options when signing
+    for (name, value) in lower_case_headers(&options.headers) {
+        if name != "host" && !headers.iter().any(|(n, _)| *n == name) {
+            headers.push((name, value));
+        }
+    }
+    for (name, value) in headers {
+        args.extend(["-H".to_string(), quote(&format!("{}: {}", name, value))]);
+    }
+    // brackets and braces are URL globbing patterns for `curl`
+    if url.as_str().contains(['[', ']', '{', '}']) {
+        args.push("--globoff".to_string());
+    }
+    args.push(quote(url.as_str()));
+    Ok(args.join(" "))
+}
+
+/// Quote `arg` for POSIX shells, unless it only contains safe characters.
+fn quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!("data.bin", quote("data.bin"));
+        assert_eq!("''", quote(""));
+        assert_eq!("'a b'", quote("a b"));
+        assert_eq!(r"'it'\''s'", quote("it's"));
+    }
+
+    #[test]
+    fn test_curl_command() -> Result<()> {
+        let url = Url::parse("https://play.min.io/bucket/my%20key").chain_err(|| "Invalid URL")?;
+        let command = |method: &Method, options: &SigningOptions, body: Option<&str>| {
+            curl_command(
+                &url,
+                method,
+                "access",
+                "secret",
+                &Region::UsEast1,
+                "s3",
+                "UNSIGNED-PAYLOAD",
+                options,
+                body,
+            )
+        };
+        let get = command(&Method::Get, &SigningOptions::default(), None)?;
+        assert!(get.starts_with(
+            "curl -X GET -H 'x-amz-content-sha256: UNSIGNED-PAYLOAD' -H 'x-amz-date: "
+        ));
+        assert!(get.contains(" -H 'authorization: AWS4-HMAC-SHA256 Credential=access/"));
+        assert!(get.ends_with(" https://play.min.io/bucket/my%20key"));
+        assert!(!get.contains("host:"));
+        let head = command(&Method::Head, &SigningOptions::default(), None)?;
+        assert!(head.starts_with("curl --head -H "));
+        let mut headers = BTreeMap::new();
+        headers.insert("X-Amz-Meta-Author".to_string(), "O'Brien".to_string());
+        let options = SigningOptions {
+            include_default_port: true,
+            headers,
+            ..Default::default()
+        };
+        let put = command(&Method::Put, &options, Some("my file.txt"))?;
+        assert!(
+            put.starts_with("curl -X PUT --upload-file 'my file.txt' -H 'host: play.min.io:443' ")
+        );
+        assert!(put.contains(r" -H 'x-amz-meta-author: O'\''Brien' "));
+        assert!(
+            put.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-meta-author,")
+        );
+        Ok(())
+    }
+}
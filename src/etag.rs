@@ -0,0 +1,142 @@
+//! ETags of multipart uploads.
+//!
+//! S3 assigns objects created with a multipart upload an ETag which is not the
+//! MD5 digest of the object but the MD5 digest of the concatenated MD5 digests
+//! of the parts, followed by `-` and the number of parts. The functions in this
+//! module compute it locally to detect corrupted uploads without additional
+//! checksums.
+//!
+//! The ETag of objects encrypted with SSE-C or SSE-KMS is not derived from the
+//! MD5 digest of the data and cannot be verified.
+
+use crate::errors::*;
+use md5::{Digest, Md5};
+use std::io::Read;
+
+// -----------------------------------------------------------------------------
+/// Return the MD5 digest of a part.
+pub fn part_md5(data: &[u8]) -> [u8; 16] {
+    Md5::digest(data).into()
+}
+
+/// Compute the ETag of a multipart upload from the MD5 digests of its parts,
+/// e.g. `3858f62230ac3c915f300c664312c11f-2`; the returned ETag is not quoted.
+///
+/// ```
+/// let parts = [s3v4::part_md5(b"hello "), s3v4::part_md5(b"world")];
+/// let etag = s3v4::compute_multipart_etag(&parts);
+/// assert!(etag.ends_with("-2"));
+/// ```
+pub fn compute_multipart_etag(part_md5s: &[[u8; 16]]) -> String {
+    let mut hasher = Md5::new();
+    for md5 in part_md5s {
+        hasher.update(md5);
+    }
+    format!("{}-{}", hex::encode(hasher.finalize()), part_md5s.len())
+}
+
+/// Compute the ETag of a multipart upload of the data read from `reader`,
+/// split in parts of `part_size` bytes. Empty data is uploaded as a single
+/// empty part.
+pub fn compute_multipart_etag_from_reader(mut reader: impl Read, part_size: u64) -> Result<String> {
+    if part_size == 0 {
+        bail!("Part size must be greater than zero");
+    }
+    let mut md5s = Vec::new();
+    let mut buffer = vec![0_u8; 64 * 1024];
+    loop {
+        let mut hasher = Md5::new();
+        let mut remaining = part_size;
+        while remaining > 0 {
+            let len = remaining.min(buffer.len() as u64) as usize;
+            let n = reader
+                .read(&mut buffer[..len])
+                .chain_err(|| "Error reading data")?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            remaining -= n as u64;
+        }
+        // stop at the first empty part, unless the data is empty
+        if remaining == part_size && !md5s.is_empty() {
+            break;
+        }
+        md5s.push(hasher.finalize().into());
+        if remaining > 0 {
+            break;
+        }
+    }
+    Ok(compute_multipart_etag(&md5s))
+}
+
+/// Compare the ETag returned by the server with the expected one, ignoring
+/// quotes and case. Return an [ErrorKind::EtagMismatch] error if they differ.
+pub fn verify_etag(expected: &str, actual: &str) -> Result<()> {
+    let unquote = |etag: &str| etag.trim().trim_matches('"').to_ascii_lowercase();
+    if unquote(expected) != unquote(actual) {
+        return Err(ErrorKind::EtagMismatch(expected.to_string(), actual.to_string()).into());
+    }
+    Ok(())
+}
+
+/// Verify the ETag returned by the server after a multipart upload of the data
+/// read from `reader` in parts of `part_size` bytes.
+///
+/// ```no_run
+/// # fn main() -> s3v4::Result<()> {
+/// # let etag = String::new();
+/// let file = std::fs::File::open("data.bin").unwrap();
+/// s3v4::verify_multipart_etag(file, 8 * 1024 * 1024, &etag)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_multipart_etag(reader: impl Read, part_size: u64, etag: &str) -> Result<()> {
+    verify_etag(
+        &compute_multipart_etag_from_reader(reader, part_size)?,
+        etag,
+    )
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_multipart_etag() -> Result<()> {
+        let data = (0..25_u8).collect::<Vec<_>>();
+        let parts = data.chunks(10).map(part_md5).collect::<Vec<_>>();
+        let etag = compute_multipart_etag(&parts);
+        assert_eq!(3, parts.len());
+        assert!(etag.ends_with("-3"));
+        assert_eq!(etag, compute_multipart_etag_from_reader(&data[..], 10)?);
+        // data size multiple of the part size: no trailing empty part
+        assert_eq!(
+            compute_multipart_etag(&[part_md5(&data[..10]), part_md5(&data[10..20])]),
+            compute_multipart_etag_from_reader(&data[..20], 10)?
+        );
+        assert_eq!(
+            compute_multipart_etag(&[part_md5(b"")]),
+            compute_multipart_etag_from_reader(&b""[..], 10)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_etag() -> Result<()> {
+        const PART_SIZE: u64 = 5 * 1024 * 1024;
+        let data = (0..2 * PART_SIZE + 12345)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect::<Vec<_>>();
+        let etag = "\"9f1e905d773ab5b5f3eda1ec53ad9cb4-3\"";
+        verify_multipart_etag(&data[..], PART_SIZE, etag)?;
+        verify_etag(&etag.to_uppercase(), etag)?;
+        match verify_multipart_etag(&data[1..], PART_SIZE, etag) {
+            Err(Error(ErrorKind::EtagMismatch(..), _)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+//! Decoder of the event streams returned by `SelectObjectContent`, available
+//! with the `event-stream` feature.
+//!
+//! Each message is made of a prelude with the total and headers lengths and
+//! their CRC32, the headers, the payload and the CRC32 of the whole message.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/API/RESTSelectObjectAppendix.html)
+
+use crate::errors::*;
+use crate::xml;
+use base64::Engine;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// Length of the prelude: total length, headers length and prelude CRC.
+const PRELUDE_LEN: usize = 12;
+/// Length of the trailing message CRC.
+const CRC_LEN: usize = 4;
+/// Maximum length of a message.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024 + 128 * 1024;
+
+// -----------------------------------------------------------------------------
+/// Message of an event stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventStreamMessage {
+    /// Headers, e.g. `:message-type` and `:event-type`; values which are not
+    /// strings are converted to their textual representation, byte arrays are
+    /// base64 encoded.
+    pub headers: BTreeMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl EventStreamMessage {
+    /// Return the value of a header.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Decode the message at the beginning of `data`, returning it with its length,
+/// or `None` if `data` does not contain a whole message.
+pub fn decode_message(data: &[u8]) -> Result<Option<(EventStreamMessage, usize)>> {
+    if data.len() < PRELUDE_LEN {
+        return Ok(None);
+    }
+    let total_len = read_u32(&data[0..4]) as usize;
+    let headers_len = read_u32(&data[4..8]) as usize;
+    if crc32fast::hash(&data[..8]) != read_u32(&data[8..12]) {
+        bail!("Invalid event stream prelude checksum");
+    }
+    if total_len > MAX_MESSAGE_LEN || total_len < PRELUDE_LEN + headers_len + CRC_LEN {
+        bail!(
+            "Invalid event stream message length {} (headers: {})",
+            total_len,
+            headers_len
+        );
+    }
+    if data.len() < total_len {
+        return Ok(None);
+    }
+    let crc_start = total_len - CRC_LEN;
+    if crc32fast::hash(&data[..crc_start]) != read_u32(&data[crc_start..total_len]) {
+        bail!("Invalid event stream message checksum");
+    }
+    let headers_end = PRELUDE_LEN + headers_len;
+    let message = EventStreamMessage {
+        headers: decode_headers(&data[PRELUDE_LEN..headers_end])?,
+        payload: data[headers_end..crc_start].to_vec(),
+    };
+    Ok(Some((message, total_len)))
+}
+
+fn decode_headers(mut data: &[u8]) -> Result<BTreeMap<String, String>> {
+    let mut headers = BTreeMap::new();
+    while !data.is_empty() {
+        let name_len = take(&mut data, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(take(&mut data, name_len)?).into_owned();
+        let value = match take(&mut data, 1)?[0] {
+            0 => "true".to_string(),
+            1 => "false".to_string(),
+            2 => (take(&mut data, 1)?[0] as i8).to_string(),
+            3 => {
+                let value = take(&mut data, 2)?;
+                i16::from_be_bytes([value[0], value[1]]).to_string()
+            }
+            4 => (read_u32(take(&mut data, 4)?) as i32).to_string(),
+            // 8: timestamp, in milliseconds since the epoch
+            5 | 8 => {
+                let mut value = [0_u8; 8];
+                value.copy_from_slice(take(&mut data, 8)?);
+                i64::from_be_bytes(value).to_string()
+            }
+            t @ (6 | 7) => {
+                let len = take(&mut data, 2)?;
+                let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+                let value = take(&mut data, len)?;
+                if t == 7 {
+                    String::from_utf8_lossy(value).into_owned()
+                } else {
+                    base64::engine::general_purpose::STANDARD.encode(value)
+                }
+            }
+            9 => hex::encode(take(&mut data, 16)?),
+            t => bail!("Invalid type {} of event stream header '{}'", t, name),
+        };
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Split the first `len` bytes from `data`.
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        bail!("Truncated event stream header");
+    }
+    let (value, rest) = data.split_at(len);
+    *data = rest;
+    Ok(value)
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+// -----------------------------------------------------------------------------
+/// Number of bytes scanned, processed and returned by a query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SelectStats {
+    pub bytes_scanned: u64,
+    pub bytes_processed: u64,
+    pub bytes_returned: u64,
+}
+
+/// Event of a `SelectObjectContent` response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SelectEvent {
+    /// Records returned by the query, possibly splitting a record.
+    Records(Vec<u8>),
+    /// Periodic progress, only sent when requested.
+    Progress(SelectStats),
+    /// Final statistics.
+    Stats(SelectStats),
+    /// Keep-alive message.
+    Continuation,
+    /// End of the results: a stream ending without this event is truncated.
+    End,
+}
+
+/// Iterator over the events of a `SelectObjectContent` response read from
+/// `reader`, e.g. the body of the HTTP response.
+///
+/// Error events are returned as errors; the iteration stops after the
+/// [SelectEvent::End] event or the first error.
+pub struct SelectEvents<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> SelectEvents<R> {
+    pub fn new(reader: R) -> Self {
+        SelectEvents {
+            reader,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Write the returned records to `writer` and return the number of bytes
+    /// written. An error is returned if the stream is truncated.
+    pub fn read_records(self, writer: &mut impl Write) -> Result<u64> {
+        let mut len = 0;
+        for event in self {
+            if let SelectEvent::Records(records) = event? {
+                writer
+                    .write_all(&records)
+                    .chain_err(|| "Error writing records")?;
+                len += records.len() as u64;
+            }
+        }
+        Ok(len)
+    }
+
+    fn next_event(&mut self) -> Result<Option<SelectEvent>> {
+        let message = loop {
+            if let Some((message, len)) = decode_message(&self.buffer)? {
+                self.buffer.drain(..len);
+                break message;
+            }
+            let mut chunk = [0_u8; 64 * 1024];
+            let n = self
+                .reader
+                .read(&mut chunk)
+                .chain_err(|| "Error reading event stream")?;
+            if n == 0 {
+                bail!("Event stream ended before the End event");
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        };
+        if message.header(":message-type") == Some("error") {
+            bail!(
+                "S3 Select error {}: {}",
+                message.header(":error-code").unwrap_or_default(),
+                message.header(":error-message").unwrap_or_default()
+            );
+        }
+        let stats = |payload: &[u8]| -> Result<SelectStats> {
+            let payload = String::from_utf8_lossy(payload);
+            let value = |tag| -> Result<u64> {
+                xml::text(&payload, tag)
+                    .and_then(|v| v.trim().parse().ok())
+                    .chain_err(|| format!("Missing or invalid {}", tag))
+            };
+            Ok(SelectStats {
+                bytes_scanned: value("BytesScanned")?,
+                bytes_processed: value("BytesProcessed")?,
+                bytes_returned: value("BytesReturned")?,
+            })
+        };
+        Ok(match message.header(":event-type") {
+            Some("Records") => Some(SelectEvent::Records(message.payload)),
+            Some("Progress") => Some(SelectEvent::Progress(stats(&message.payload)?)),
+            Some("Stats") => Some(SelectEvent::Stats(stats(&message.payload)?)),
+            Some("Cont") => Some(SelectEvent::Continuation),
+            Some("End") => Some(SelectEvent::End),
+            // ignore unknown events
+            _ => None,
+        })
+    }
+}
+
+impl<R: Read> Iterator for SelectEvents<R> {
+    type Item = Result<SelectEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.next_event() {
+                Ok(None) => {}
+                Ok(Some(event)) => {
+                    self.done = event == SelectEvent::End;
+                    return Some(Ok(event));
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a message with string headers.
+    fn encode(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+        let mut encoded_headers = Vec::new();
+        for (name, value) in headers {
+            encoded_headers.push(name.len() as u8);
+            encoded_headers.extend_from_slice(name.as_bytes());
+            encoded_headers.push(7);
+            encoded_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            encoded_headers.extend_from_slice(value.as_bytes());
+        }
+        let total_len = PRELUDE_LEN + encoded_headers.len() + payload.len() + CRC_LEN;
+        let mut message = Vec::new();
+        message.extend_from_slice(&(total_len as u32).to_be_bytes());
+        message.extend_from_slice(&(encoded_headers.len() as u32).to_be_bytes());
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+        message.extend_from_slice(&encoded_headers);
+        message.extend_from_slice(payload);
+        message.extend_from_slice(&crc32fast::hash(&message).to_be_bytes());
+        message
+    }
+
+    fn event(event_type: &str, payload: &[u8]) -> Vec<u8> {
+        encode(
+            &[(":message-type", "event"), (":event-type", event_type)],
+            payload,
+        )
+    }
+
+    #[test]
+    fn test_decode_message() -> Result<()> {
+        // message without headers and payload from the AWS event stream test suite
+        let empty = hex::decode("000000100000000005c248eb7d98c8ff").unwrap();
+        let (message, len) = decode_message(&empty)?.unwrap();
+        assert_eq!(16, len);
+        assert!(message.headers.is_empty() && message.payload.is_empty());
+        assert!(decode_message(&empty[..15])?.is_none());
+        let mut corrupted = empty.clone();
+        corrupted[13] ^= 1;
+        assert!(decode_message(&corrupted).is_err());
+        let records = event("Records", b"a,b\n");
+        let (message, _) = decode_message(&records)?.unwrap();
+        assert_eq!(Some("Records"), message.header(":event-type"));
+        assert_eq!(b"a,b\n", &message.payload[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_events() -> Result<()> {
+        let stats = b"<Stats><BytesScanned>10</BytesScanned><BytesProcessed>10</BytesProcessed>\
+                      <BytesReturned>8</BytesReturned></Stats>";
+        let mut stream = Vec::new();
+        stream.extend(event("Records", b"a,b\n"));
+        stream.extend(event("Cont", b""));
+        stream.extend(event("Records", b"c,d\n"));
+        stream.extend(event("Stats", stats));
+        stream.extend(event("End", b""));
+        let events = SelectEvents::new(&stream[..]).collect::<Result<Vec<_>>>()?;
+        assert_eq!(5, events.len());
+        assert_eq!(
+            SelectEvent::Stats(SelectStats {
+                bytes_scanned: 10,
+                bytes_processed: 10,
+                bytes_returned: 8
+            }),
+            events[3]
+        );
+        let mut records = Vec::new();
+        assert_eq!(
+            8,
+            SelectEvents::new(&stream[..]).read_records(&mut records)?
+        );
+        assert_eq!(b"a,b\nc,d\n", &records[..]);
+        // truncated stream
+        let truncated = &stream[..stream.len() - 1];
+        assert!(SelectEvents::new(truncated)
+            .read_records(&mut Vec::new())
+            .is_err());
+        let error = encode(
+            &[
+                (":message-type", "error"),
+                (":error-code", "InvalidQuery"),
+                (":error-message", "Syntax error"),
+            ],
+            b"",
+        );
+        let err = SelectEvents::new(&error[..]).next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("InvalidQuery"));
+        Ok(())
+    }
+}
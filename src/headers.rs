@@ -0,0 +1,287 @@
+//! Helpers generating the values of S3 specific request headers.
+//!
+//! The generated headers must be both signed, by adding them to
+//! [SigningOptions::headers](crate::SigningOptions::headers), and sent with the request.
+
+use crate::errors::*;
+use base64::Engine;
+use md5::{Digest, Md5};
+use std::collections::BTreeMap;
+use urlencoding::encode as url_encode;
+
+/// Name of the header containing the tag set of an object.
+pub const TAGGING_HEADER: &str = "x-amz-tagging";
+
+/// Prefix of user-defined metadata headers.
+pub const METADATA_PREFIX: &str = "x-amz-meta-";
+
+/// Name of the header selecting server-side encryption with S3 (`AES256`) or KMS
+/// (`aws:kms`) managed keys.
+pub const SSE_HEADER: &str = "x-amz-server-side-encryption";
+
+/// Name of the header containing the ID of the KMS key used for SSE-KMS.
+pub const SSE_KMS_KEY_ID_HEADER: &str = "x-amz-server-side-encryption-aws-kms-key-id";
+
+/// Name of the header containing the SSE-C encryption algorithm.
+pub const SSE_CUSTOMER_ALGORITHM_HEADER: &str = "x-amz-server-side-encryption-customer-algorithm";
+
+/// Name of the header containing the base64-encoded SSE-C key.
+pub const SSE_CUSTOMER_KEY_HEADER: &str = "x-amz-server-side-encryption-customer-key";
+
+/// Name of the header containing the base64-encoded MD5 digest of the SSE-C key.
+pub const SSE_CUSTOMER_KEY_MD5_HEADER: &str = "x-amz-server-side-encryption-customer-key-md5";
+
+/// Length of SSE-C keys (AES-256).
+const SSE_CUSTOMER_KEY_LEN: usize = 32;
+
+/// Maximum size of user-defined metadata: sum of the UTF-8 lengths of all keys and values.
+const MAX_METADATA_SIZE: usize = 2048;
+const MAX_TAGS: usize = 10;
+const MAX_TAG_KEY_LEN: usize = 128;
+const MAX_TAG_VALUE_LEN: usize = 256;
+
+// -----------------------------------------------------------------------------
+/// Generate the value of the `x-amz-tagging` header from a tag set.
+///
+/// Keys and values are URL-encoded and joined as in a query string
+/// (`key1=value1&key2=value2`). An error is returned if the tag set does not
+/// satisfy the S3 limits: at most 10 tags with unique, non-empty keys of up to 128
+/// characters and values of up to 256 characters.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// let mut options = s3v4::SigningOptions::default();
+/// options.headers.insert(
+///     s3v4::TAGGING_HEADER.to_string(),
+///     s3v4::tagging_header(&[("project", "s3v4"), ("stage", "test & dev")])?,
+/// );
+/// assert_eq!("project=s3v4&stage=test%20%26%20dev", options.headers[s3v4::TAGGING_HEADER]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn tagging_header<K: AsRef<str>, V: AsRef<str>>(tags: &[(K, V)]) -> Result<String> {
+    if tags.len() > MAX_TAGS {
+        bail!("Too many tags: {} (max {})", tags.len(), MAX_TAGS);
+    }
+    for (i, (key, value)) in tags.iter().enumerate() {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        if key.is_empty() || key.chars().count() > MAX_TAG_KEY_LEN {
+            bail!(
+                "Invalid tag key length: '{}' (1 to {} characters)",
+                key,
+                MAX_TAG_KEY_LEN
+            );
+        }
+        if value.chars().count() > MAX_TAG_VALUE_LEN {
+            bail!(
+                "Tag value too long for key '{}' (max {} characters)",
+                key,
+                MAX_TAG_VALUE_LEN
+            );
+        }
+        if tags[..i].iter().any(|(k, _)| k.as_ref() == key) {
+            bail!("Duplicate tag key: '{}'", key);
+        }
+    }
+    Ok(tags
+        .iter()
+        .map(|(k, v)| format!("{}={}", url_encode(k.as_ref()), url_encode(v.as_ref())))
+        .collect::<Vec<_>>()
+        .join("&"))
+}
+
+// -----------------------------------------------------------------------------
+/// Generate `x-amz-meta-*` headers from user-defined metadata.
+///
+/// Keys may be specified with or without the `x-amz-meta-` prefix and are converted
+/// to lower case; they must be valid HTTP header names. Values containing non-ASCII
+/// or control characters are encoded as RFC 2047 encoded words (`=?UTF-8?B?...?=`),
+/// the same way S3 returns them, as are values with leading or trailing spaces,
+/// which would otherwise be removed from the header; sequential spaces are kept.
+/// An error is returned if a key is invalid or repeated or if the total metadata
+/// size exceeds the 2 KB S3 limit.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// let mut options = s3v4::SigningOptions::default();
+/// options
+///     .headers
+///     .extend(s3v4::metadata_headers([("Author", "Ugo"), ("city", "Zürich")])?);
+/// assert_eq!("Ugo", options.headers["x-amz-meta-author"]);
+/// assert_eq!("=?UTF-8?B?WsO8cmljaA==?=", options.headers["x-amz-meta-city"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn metadata_headers<I, K, V>(metadata: I) -> Result<BTreeMap<String, String>>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut headers = BTreeMap::new();
+    let mut size = 0;
+    for (key, value) in metadata {
+        let key = key.as_ref().to_lowercase();
+        let key = key.strip_prefix(METADATA_PREFIX).unwrap_or(&key);
+        let value = value.as_ref();
+        if key.is_empty() || !key.chars().all(is_token_char) {
+            bail!("Invalid metadata key: '{}'", key);
+        }
+        size += key.len() + value.len();
+        if size > MAX_METADATA_SIZE {
+            bail!(
+                "User-defined metadata exceeds the maximum size of {} bytes",
+                MAX_METADATA_SIZE
+            );
+        }
+        let plain =
+            value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) && value.trim() == value;
+        let value = if plain {
+            value.to_string()
+        } else {
+            format!(
+                "=?UTF-8?B?{}?=",
+                base64::engine::general_purpose::STANDARD.encode(value)
+            )
+        };
+        if headers
+            .insert(METADATA_PREFIX.to_string() + key, value)
+            .is_some()
+        {
+            bail!("Duplicate metadata key: '{}'", key);
+        }
+    }
+    Ok(headers)
+}
+
+// -----------------------------------------------------------------------------
+/// Generate the headers requesting server-side encryption with a customer
+/// provided key (SSE-C).
+///
+/// `key` is the raw 256-bit key: the headers contain the algorithm, the
+/// base64-encoded key and the base64-encoded MD5 digest of the key. The same
+/// headers must be sent to read the object.
+///
+/// When pre-signing a URL the headers become part of the signed headers and
+/// must be sent by whoever uses the URL, while the key is not included in the URL:
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// let key = [7_u8; 32];
+/// let mut options = s3v4::PresignOptions::default();
+/// options.signing.headers.extend(s3v4::sse_c_headers(&key)?);
+/// let endpoint = url::Url::parse("https://play.min.io").unwrap();
+/// let url = s3v4::presign_put_object("access", "secret", &endpoint, "bucket", "key", 600, &options)?;
+/// assert!(url.contains("x-amz-server-side-encryption-customer-key-md5"));
+/// assert!(!url.contains("BwcHBwcH"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn sse_c_headers(key: &[u8]) -> Result<BTreeMap<String, String>> {
+    if key.len() != SSE_CUSTOMER_KEY_LEN {
+        bail!(
+            "Invalid SSE-C key length: {} bytes, expected a {} bytes (256 bit) key; \
+             the key must be the raw key, not its base64 encoding",
+            key.len(),
+            SSE_CUSTOMER_KEY_LEN
+        );
+    }
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let mut headers = BTreeMap::new();
+    headers.insert(
+        SSE_CUSTOMER_ALGORITHM_HEADER.to_string(),
+        "AES256".to_string(),
+    );
+    headers.insert(SSE_CUSTOMER_KEY_HEADER.to_string(), base64.encode(key));
+    headers.insert(
+        SSE_CUSTOMER_KEY_MD5_HEADER.to_string(),
+        base64.encode(Md5::digest(key)),
+    );
+    Ok(headers)
+}
+
+/// Generate the headers requesting server-side encryption with a KMS key
+/// (SSE-KMS); without `key_id` the AWS managed key is used.
+///
+/// ```
+/// let headers = s3v4::sse_kms_headers(Some("alias/my-key"));
+/// assert_eq!("aws:kms", headers[s3v4::SSE_HEADER]);
+/// assert_eq!("alias/my-key", headers[s3v4::SSE_KMS_KEY_ID_HEADER]);
+/// ```
+pub fn sse_kms_headers(key_id: Option<&str>) -> BTreeMap<String, String> {
+    let mut headers = BTreeMap::new();
+    headers.insert(SSE_HEADER.to_string(), "aws:kms".to_string());
+    if let Some(key_id) = key_id {
+        headers.insert(SSE_KMS_KEY_ID_HEADER.to_string(), key_id.to_string());
+    }
+    headers
+}
+
+/// Return `true` if the character can be part of an HTTP token (RFC 7230, section 3.2.6).
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tagging_header() -> Result<()> {
+        assert_eq!("", tagging_header::<&str, &str>(&[])?);
+        assert_eq!(
+            "a%2Fb=c%3Dd&empty=",
+            tagging_header(&[("a/b", "c=d"), ("empty", "")])?
+        );
+        assert!(tagging_header(&[("", "value")]).is_err());
+        assert!(tagging_header(&[("key", "1"), ("key", "2")]).is_err());
+        assert!(tagging_header(&[("key", "v".repeat(257))]).is_err());
+        let too_many = (0..11).map(|i| (i.to_string(), "")).collect::<Vec<_>>();
+        assert!(tagging_header(&too_many).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_headers() -> Result<()> {
+        let headers = metadata_headers([("X-Amz-Meta-Foo", "bar"), ("tab", "a\tb")])?;
+        assert_eq!(2, headers.len());
+        assert_eq!("bar", headers["x-amz-meta-foo"]);
+        assert_eq!("=?UTF-8?B?YQli?=", headers["x-amz-meta-tab"]);
+        // sequential spaces are sent as is and signed as a single space like S3
+        // does, while leading and trailing spaces would be lost
+        let headers = metadata_headers([("inner", "a   b"), ("outer", " a ")])?;
+        assert_eq!("a   b", headers["x-amz-meta-inner"]);
+        assert_eq!("=?UTF-8?B?IGEg?=", headers["x-amz-meta-outer"]);
+        assert_eq!(
+            "x-amz-meta-inner:a b\nx-amz-meta-outer:=?UTF-8?B?IGEg?=",
+            crate::canonical_header_string(&headers)
+        );
+        assert!(metadata_headers([("my key", "value")]).is_err());
+        assert!(metadata_headers([("", "value")]).is_err());
+        assert!(metadata_headers([("key", "1"), ("KEY", "2")]).is_err());
+        assert!(metadata_headers([("key", "v".repeat(2046))]).is_err());
+        assert!(metadata_headers([("key", "v".repeat(2045))]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sse_headers() -> Result<()> {
+        let key = (0..32).collect::<Vec<u8>>();
+        let headers = sse_c_headers(&key)?;
+        assert_eq!("AES256", headers[SSE_CUSTOMER_ALGORITHM_HEADER]);
+        assert_eq!(
+            "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=",
+            headers[SSE_CUSTOMER_KEY_HEADER]
+        );
+        assert_eq!(
+            "tP/LI3N87DFaSk0aoqYgzg==",
+            headers[SSE_CUSTOMER_KEY_MD5_HEADER]
+        );
+        // base64-encoded key passed by mistake
+        assert!(sse_c_headers(headers[SSE_CUSTOMER_KEY_HEADER].as_bytes()).is_err());
+        assert_eq!(1, sse_kms_headers(None).len());
+        Ok(())
+    }
+}
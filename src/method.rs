@@ -0,0 +1,83 @@
+//! HTTP request methods.
+
+use crate::errors::*;
+use std::fmt;
+use std::str::FromStr;
+
+// -----------------------------------------------------------------------------
+/// HTTP method of the request to sign.
+///
+/// Parsing is case insensitive and always returns upper case methods, e.g.
+/// both `"get"` and `"GET"` are parsed as [Method::Get] and `"patch"` as
+/// `Method::Custom("PATCH")`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Put,
+    Post,
+    Delete,
+    Head,
+    /// Any other method, used as is in the canonical request.
+    Custom(String),
+}
+
+impl Method {
+    /// Return the method name as it appears in the canonical request.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Put => "PUT",
+            Method::Post => "POST",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Custom(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Method {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let method = s.to_ascii_uppercase();
+        Ok(match method.as_str() {
+            "GET" => Method::Get,
+            "PUT" => Method::Put,
+            "POST" => Method::Post,
+            "DELETE" => Method::Delete,
+            "HEAD" => Method::Head,
+            // method names are HTTP tokens (RFC 7230, section 3.2.6)
+            _ if !method.is_empty()
+                && method
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)) =>
+            {
+                Method::Custom(method)
+            }
+            _ => bail!("Invalid HTTP method: '{}'", s),
+        })
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_method() -> Result<()> {
+        assert_eq!(Method::Get, "get".parse()?);
+        assert_eq!(Method::Head, "HEAD".parse()?);
+        assert_eq!(Method::Custom("PATCH".to_string()), "Patch".parse()?);
+        assert_eq!("DELETE", Method::Delete.to_string());
+        assert!("".parse::<Method>().is_err());
+        assert!("GET /".parse::<Method>().is_err());
+        Ok(())
+    }
+}
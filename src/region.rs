@@ -0,0 +1,176 @@
+//! Signing regions.
+
+use crate::errors::*;
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! regions {
+    ($($variant:ident => $name:literal,)*) => {
+        // ---------------------------------------------------------------------
+        /// Region used to sign requests.
+        ///
+        /// Standard AWS regions have their own variant; S3-compatible servers
+        /// using any other region name are supported through [Region::Custom].
+        ///
+        /// Names starting with an AWS partition prefix must have the shape of
+        /// an AWS region name, `<prefix>-<direction>-<number>`: AWS regions
+        /// added after this release (e.g. `ap-east-2`) are parsed as
+        /// [Region::Custom] while typos (e.g. `us-east1`) fail. All other
+        /// names are parsed as [Region::Custom].
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum Region {
+            $(
+                #[doc = concat!("`", $name, "`")]
+                $variant,
+            )*
+            /// Region of an S3-compatible server, used as is in the signature scope.
+            Custom(String),
+        }
+
+        impl Region {
+            /// Return the region name as it appears in the signature scope.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Region::$variant => $name,)*
+                    Region::Custom(name) => name,
+                }
+            }
+
+            /// Return the standard AWS region with the given name, if any.
+            fn from_aws_name(name: &str) -> Option<Region> {
+                match name {
+                    $($name => Some(Region::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+regions! {
+    UsEast1 => "us-east-1",
+    UsEast2 => "us-east-2",
+    UsWest1 => "us-west-1",
+    UsWest2 => "us-west-2",
+    AfSouth1 => "af-south-1",
+    ApEast1 => "ap-east-1",
+    ApSouth1 => "ap-south-1",
+    ApSouth2 => "ap-south-2",
+    ApSoutheast1 => "ap-southeast-1",
+    ApSoutheast2 => "ap-southeast-2",
+    ApSoutheast3 => "ap-southeast-3",
+    ApSoutheast4 => "ap-southeast-4",
+    ApSoutheast5 => "ap-southeast-5",
+    ApSoutheast7 => "ap-southeast-7",
+    ApNortheast1 => "ap-northeast-1",
+    ApNortheast2 => "ap-northeast-2",
+    ApNortheast3 => "ap-northeast-3",
+    CaCentral1 => "ca-central-1",
+    CaWest1 => "ca-west-1",
+    EuCentral1 => "eu-central-1",
+    EuCentral2 => "eu-central-2",
+    EuWest1 => "eu-west-1",
+    EuWest2 => "eu-west-2",
+    EuWest3 => "eu-west-3",
+    EuSouth1 => "eu-south-1",
+    EuSouth2 => "eu-south-2",
+    EuNorth1 => "eu-north-1",
+    IlCentral1 => "il-central-1",
+    MeSouth1 => "me-south-1",
+    MeCentral1 => "me-central-1",
+    MxCentral1 => "mx-central-1",
+    SaEast1 => "sa-east-1",
+    UsGovEast1 => "us-gov-east-1",
+    UsGovWest1 => "us-gov-west-1",
+    CnNorth1 => "cn-north-1",
+    CnNorthwest1 => "cn-northwest-1",
+}
+
+/// Prefixes of AWS region names: names starting with one of these and not
+/// matching a standard region must have the shape of an AWS region name.
+const AWS_REGION_PREFIXES: &[&str] = &[
+    "us-", "eu-", "ap-", "ca-", "sa-", "me-", "af-", "il-", "mx-", "cn-",
+];
+
+/// Qualifiers following the prefix of AWS regions in isolated partitions,
+/// e.g. `us-gov-west-1`.
+const AWS_REGION_QUALIFIERS: &[&str] = &["gov", "iso", "isob", "isof", "isoe"];
+
+/// Directions of AWS region names, e.g. `southeast` in `ap-southeast-1`.
+const AWS_REGION_DIRECTIONS: &[&str] = &[
+    "east",
+    "west",
+    "north",
+    "south",
+    "central",
+    "northeast",
+    "northwest",
+    "southeast",
+    "southwest",
+];
+
+/// Return `true` if `name` has the shape `<prefix>-[<qualifier>-]<direction>-<number>`.
+fn is_aws_region_shape(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('-').collect();
+    let (direction, number) = match parts.as_slice() {
+        [_, direction, number] => (direction, number),
+        [_, qualifier, direction, number] if AWS_REGION_QUALIFIERS.contains(qualifier) => {
+            (direction, number)
+        }
+        _ => return false,
+    };
+    AWS_REGION_DIRECTIONS.contains(direction)
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Region {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(region) = Region::from_aws_name(s) {
+            return Ok(region);
+        }
+        if AWS_REGION_PREFIXES.iter().any(|p| s.starts_with(p)) && !is_aws_region_shape(s) {
+            bail!("Unknown AWS region: '{}'", s);
+        }
+        if s.is_empty()
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid region name: '{}'", s);
+        }
+        Ok(Region::Custom(s.to_string()))
+    }
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_region() -> Result<()> {
+        assert_eq!(Region::UsEast1, "us-east-1".parse()?);
+        assert_eq!(Region::UsGovWest1, "us-gov-west-1".parse()?);
+        assert_eq!("eu-central-1", Region::EuCentral1.to_string());
+        assert_eq!(Region::Custom("garage".to_string()), "garage".parse()?);
+        for name in ["ap-east-2", "us-isob-east-1", "eusc-de-east-1"] {
+            assert_eq!(Region::Custom(name.to_string()), name.parse()?);
+        }
+        assert!("us-east1".parse::<Region>().is_err());
+        assert!("eu-centrall-1".parse::<Region>().is_err());
+        assert!("us-central".parse::<Region>().is_err());
+        assert!("us-gov-west-x".parse::<Region>().is_err());
+        assert!("".parse::<Region>().is_err());
+        assert!("my region".parse::<Region>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+//! `SelectObjectContent` requests.
+//!
+//! S3 Select filters the content of CSV, JSON and Parquet objects on the server
+//! with an SQL expression. The request is a `POST` with an XML body which, unlike
+//! the body of uploads, is hashed and included in the signature.
+//!
+//! The response is an event stream, which can be decoded with
+//! [SelectEvents](crate::SelectEvents) when the `event-stream` feature is enabled.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/API/API_SelectObjectContent.html)
+
+use crate::crypto::{Backend, Crypto};
+use crate::errors::*;
+use crate::xml::escape;
+use crate::{
+    object_url, signature_with_options, Addressing, Method, Region, Signature, SigningOptions,
+};
+use std::fmt::Write;
+use std::ops::Range;
+use url::Url;
+
+// -----------------------------------------------------------------------------
+/// Compression of the queried object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionType {
+    None,
+    Gzip,
+    Bzip2,
+}
+
+impl CompressionType {
+    fn as_str(&self) -> &str {
+        match self {
+            CompressionType::None => "NONE",
+            CompressionType::Gzip => "GZIP",
+            CompressionType::Bzip2 => "BZIP2",
+        }
+    }
+}
+
+/// Use of the first line of CSV objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileHeaderInfo {
+    /// The first line contains the column names, usable in the expression.
+    Use,
+    /// The first line is skipped.
+    Ignore,
+    /// The first line is a record, columns are referenced as `_1`, `_2`...
+    None,
+}
+
+impl FileHeaderInfo {
+    fn as_str(&self) -> &str {
+        match self {
+            FileHeaderInfo::Use => "USE",
+            FileHeaderInfo::Ignore => "IGNORE",
+            FileHeaderInfo::None => "NONE",
+        }
+    }
+}
+
+/// Format of CSV objects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvInput {
+    /// Defaults to [FileHeaderInfo::None].
+    pub file_header_info: FileHeaderInfo,
+    /// Defaults to `,`.
+    pub field_delimiter: String,
+    /// Defaults to `\n`.
+    pub record_delimiter: String,
+    /// Defaults to `"`.
+    pub quote_character: String,
+    /// Prefix of comment lines, which are skipped; defaults to none.
+    pub comments: Option<String>,
+    /// Allow record delimiters inside quoted fields, defaults to `false`.
+    pub allow_quoted_record_delimiter: bool,
+}
+
+impl Default for CsvInput {
+    fn default() -> Self {
+        CsvInput {
+            file_header_info: FileHeaderInfo::None,
+            field_delimiter: ",".to_string(),
+            record_delimiter: "\n".to_string(),
+            quote_character: "\"".to_string(),
+            comments: None,
+            allow_quoted_record_delimiter: false,
+        }
+    }
+}
+
+/// Layout of JSON objects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonType {
+    /// A single JSON document, possibly spanning multiple lines.
+    Document,
+    /// One JSON document per line.
+    Lines,
+}
+
+/// Format of the queried object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    Csv(CsvInput),
+    Json(JsonType),
+    /// Parquet objects cannot be compressed.
+    Parquet,
+}
+
+/// Format of CSV records returned by the query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvOutput {
+    /// Defaults to `,`.
+    pub field_delimiter: String,
+    /// Defaults to `\n`.
+    pub record_delimiter: String,
+    /// Quote all the fields instead of only those containing delimiters,
+    /// defaults to `false`.
+    pub quote_always: bool,
+}
+
+impl Default for CsvOutput {
+    fn default() -> Self {
+        CsvOutput {
+            field_delimiter: ",".to_string(),
+            record_delimiter: "\n".to_string(),
+            quote_always: false,
+        }
+    }
+}
+
+/// Format of the records returned by the query; JSON records are separated by
+/// `record_delimiter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv(CsvOutput),
+    Json { record_delimiter: String },
+}
+
+// -----------------------------------------------------------------------------
+/// Builder of `SelectObjectContent` requests.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// use s3v4::{CsvInput, FileHeaderInfo, InputFormat, SelectRequest};
+/// let request = SelectRequest::new("SELECT s.name FROM S3Object s WHERE s.age > '30'")
+///     .with_input(InputFormat::Csv(CsvInput {
+///         file_header_info: FileHeaderInfo::Use,
+///         ..Default::default()
+///     }));
+/// let endpoint = url::Url::parse("https://play.min.io").unwrap();
+/// let url = request.url(&endpoint, "bucket", "people.csv", s3v4::Addressing::Path)?;
+/// let signature = request.signature(&url, "access", "secret", &s3v4::Region::UsEast1)?;
+/// // send a POST request to `url` with the body and the headers:
+/// // x-amz-content-sha256: request.payload_hash()?
+/// // x-amz-date: signature.date_time
+/// // authorization: signature.auth_header
+/// let body = request.body()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectRequest {
+    expression: String,
+    input: InputFormat,
+    compression: CompressionType,
+    output: OutputFormat,
+    request_progress: bool,
+    scan_range: Option<Range<u64>>,
+}
+
+impl SelectRequest {
+    /// Create a request running the SQL `expression` on a CSV object without
+    /// header, returning CSV records.
+    pub fn new(expression: &str) -> Self {
+        SelectRequest {
+            expression: expression.to_string(),
+            input: InputFormat::Csv(CsvInput::default()),
+            compression: CompressionType::None,
+            output: OutputFormat::Csv(CsvOutput::default()),
+            request_progress: false,
+            scan_range: None,
+        }
+    }
+
+    /// Set the format of the queried object.
+    pub fn with_input(mut self, input: InputFormat) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// Set the compression of the queried object.
+    pub fn with_compression(mut self, compression: CompressionType) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the format of the returned records.
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Request periodic progress events.
+    pub fn with_request_progress(mut self, request_progress: bool) -> Self {
+        self.request_progress = request_progress;
+        self
+    }
+
+    /// Only query the records starting in the specified byte range of an
+    /// uncompressed CSV or JSON lines object.
+    pub fn with_scan_range(mut self, range: Range<u64>) -> Self {
+        self.scan_range = Some(range);
+        self
+    }
+
+    /// Return the XML body of the request.
+    pub fn body(&self) -> Result<String> {
+        if self.expression.trim().is_empty() {
+            bail!("Empty SQL expression");
+        }
+        if self.input == InputFormat::Parquet && self.compression != CompressionType::None {
+            bail!("Parquet objects cannot be compressed");
+        }
+        let mut body = String::from(
+            "<SelectObjectContentRequest xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">",
+        );
+        element(&mut body, "Expression", &self.expression);
+        element(&mut body, "ExpressionType", "SQL");
+        body.push_str("<InputSerialization>");
+        element(&mut body, "CompressionType", self.compression.as_str());
+        match &self.input {
+            InputFormat::Csv(csv) => {
+                body.push_str("<CSV>");
+                element(&mut body, "FileHeaderInfo", csv.file_header_info.as_str());
+                element(&mut body, "FieldDelimiter", &csv.field_delimiter);
+                element(&mut body, "RecordDelimiter", &csv.record_delimiter);
+                element(&mut body, "QuoteCharacter", &csv.quote_character);
+                if let Some(comments) = &csv.comments {
+                    element(&mut body, "Comments", comments);
+                }
+                if csv.allow_quoted_record_delimiter {
+                    element(&mut body, "AllowQuotedRecordDelimiter", "TRUE");
+                }
+                body.push_str("</CSV>");
+            }
+            InputFormat::Json(json_type) => {
+                let json_type = match json_type {
+                    JsonType::Document => "DOCUMENT",
+                    JsonType::Lines => "LINES",
+                };
+                body.push_str("<JSON>");
+                element(&mut body, "Type", json_type);
+                body.push_str("</JSON>");
+            }
+            InputFormat::Parquet => body.push_str("<Parquet/>"),
+        }
+        body.push_str("</InputSerialization><OutputSerialization>");
+        match &self.output {
+            OutputFormat::Csv(csv) => {
+                body.push_str("<CSV>");
+                element(&mut body, "FieldDelimiter", &csv.field_delimiter);
+                element(&mut body, "RecordDelimiter", &csv.record_delimiter);
+                let quote_fields = if csv.quote_always {
+                    "ALWAYS"
+                } else {
+                    "ASNEEDED"
+                };
+                element(&mut body, "QuoteFields", quote_fields);
+                body.push_str("</CSV>");
+            }
+            OutputFormat::Json { record_delimiter } => {
+                body.push_str("<JSON>");
+                element(&mut body, "RecordDelimiter", record_delimiter);
+                body.push_str("</JSON>");
+            }
+        }
+        body.push_str("</OutputSerialization>");
+        if self.request_progress {
+            body.push_str("<RequestProgress><Enabled>TRUE</Enabled></RequestProgress>");
+        }
+        if let Some(range) = &self.scan_range {
+            if range.start >= range.end {
+                bail!("Empty scan range {:?}", range);
+            }
+            // the end of the scan range is inclusive
+            let _ = write!(
+                body,
+                "<ScanRange><Start>{}</Start><End>{}</End></ScanRange>",
+                range.start,
+                range.end - 1
+            );
+        }
+        body.push_str("</SelectObjectContentRequest>");
+        Ok(body)
+    }
+
+    /// Return the hex-encoded SHA-256 digest of the body, to be sent in the
+    /// `x-amz-content-sha256` header.
+    pub fn payload_hash(&self) -> Result<String> {
+        Ok(hex::encode(Crypto::sha256(self.body()?.as_bytes())))
+    }
+
+    /// Return the URL of the request for the specified object.
+    pub fn url(
+        &self,
+        endpoint: &Url,
+        bucket: &str,
+        key: &str,
+        addressing: Addressing,
+    ) -> Result<Url> {
+        let mut url = object_url(endpoint, bucket, key, addressing)?;
+        url.set_query(Some("select&select-type=2"));
+        Ok(url)
+    }
+
+    /// Sign the `POST` request to `url`, including the digest of the body.
+    pub fn signature(
+        &self,
+        url: &Url,
+        access: &str,
+        secret: &str,
+        region: &Region,
+    ) -> Result<Signature> {
+        self.signature_with_options(url, access, secret, region, &SigningOptions::default())
+    }
+
+    /// Sign the `POST` request to `url` like [SelectRequest::signature], using
+    /// the specified signing options.
+    pub fn signature_with_options(
+        &self,
+        url: &Url,
+        access: &str,
+        secret: &str,
+        region: &Region,
+        options: &SigningOptions,
+    ) -> Result<Signature> {
+        signature_with_options(
+            url,
+            &Method::Post,
+            access,
+            secret,
+            region,
+            "s3",
+            &self.payload_hash()?,
+            options,
+        )
+    }
+}
+
+/// Append `<tag>value</tag>` to `xml`, escaping the value.
+fn element(xml: &mut String, tag: &str, value: &str) {
+    let _ = write!(xml, "<{}>{}</{}>", tag, escape(value), tag);
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_request() -> Result<()> {
+        let request = SelectRequest::new("SELECT * FROM S3Object s WHERE s._1 < '5'")
+            .with_compression(CompressionType::Gzip)
+            .with_output(OutputFormat::Json {
+                record_delimiter: "\n".to_string(),
+            })
+            .with_request_progress(true)
+            .with_scan_range(0..100);
+        assert_eq!(
+            "<SelectObjectContentRequest xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+             <Expression>SELECT * FROM S3Object s WHERE s._1 &lt; &apos;5&apos;</Expression>\
+             <ExpressionType>SQL</ExpressionType>\
+             <InputSerialization><CompressionType>GZIP</CompressionType>\
+             <CSV><FileHeaderInfo>NONE</FileHeaderInfo><FieldDelimiter>,</FieldDelimiter>\
+             <RecordDelimiter>\n</RecordDelimiter><QuoteCharacter>&quot;</QuoteCharacter></CSV>\
+             </InputSerialization>\
+             <OutputSerialization><JSON><RecordDelimiter>\n</RecordDelimiter></JSON></OutputSerialization>\
+             <RequestProgress><Enabled>TRUE</Enabled></RequestProgress>\
+             <ScanRange><Start>0</Start><End>99</End></ScanRange>\
+             </SelectObjectContentRequest>",
+            request.body()?
+        );
+        assert_eq!(
+            hex::encode(Crypto::sha256(request.body()?.as_bytes())),
+            request.payload_hash()?
+        );
+        let endpoint = Url::parse("https://play.min.io").chain_err(|| "Error parsing url")?;
+        assert_eq!(
+            "https://play.min.io/bucket/data.csv.gz?select&select-type=2",
+            request
+                .url(&endpoint, "bucket", "data.csv.gz", Addressing::Path)?
+                .as_str()
+        );
+        let parquet = SelectRequest::new("SELECT * FROM S3Object").with_input(InputFormat::Parquet);
+        assert!(parquet.body()?.contains("<Parquet/>"));
+        assert!(parquet
+            .with_compression(CompressionType::Gzip)
+            .body()
+            .is_err());
+        assert!(SelectRequest::new(" ").body().is_err());
+        Ok(())
+    }
+}
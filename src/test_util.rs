@@ -0,0 +1,1568 @@
+//! In-process mock S3 server verifying request signatures, to integration-test
+//! signing and client code without network access or a real S3 server.
+
+use crate::crypto::{Backend, Crypto};
+use crate::errors::*;
+use crate::{
+    xml, Credentials, Method, Region, HTTP_DATE_FMT, LONG_DATETIME_FMT, SECURITY_TOKEN_HEADER,
+};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use md5::{Digest, Md5};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use url::Url;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+/// Maximum difference between the time of header-signed requests and the
+/// current time, in seconds.
+const MAX_SKEW: i64 = 15 * 60;
+/// Maximum expiration of pre-signed URLs, in seconds.
+const MAX_EXPIRES: i64 = 7 * 24 * 3600;
+
+// -----------------------------------------------------------------------------
+/// Response sent by [MockS3Server].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// Create a response with the specified status and body.
+    pub fn new(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// Add a header to the response.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Create an S3 error response, e.g. `error(404, "NoSuchKey", "...")`.
+    pub fn error(status: u16, code: &str, message: &str) -> Self {
+        error_response(status, code, message, &[])
+    }
+
+    /// Return the value of a header.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Request received by [MockS3Server].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// URL built from the `x-forwarded-host` or `host` header and the request target.
+    pub url: Url,
+    /// Headers with lower case names.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Access key of the verified signature, `None` if it was rejected.
+    pub access: Option<String>,
+    /// Status of the response.
+    pub status: u16,
+}
+
+// -----------------------------------------------------------------------------
+/// Mock S3 server listening on a local port, which verifies the SigV4
+/// signature of each request against the configured credentials before
+/// serving it.
+///
+/// Requests in header (`authorization`) or query (pre-signed URLs) form are
+/// verified as S3 does, returning `SignatureDoesNotMatch` errors with the
+/// expected canonical request and string to sign, and signed payload hashes
+/// are checked against the body. The host of requests forwarded by a reverse
+/// proxy is taken from the `x-forwarded-host` header, as with
+/// [SigningOptions::host_override]. Header-signed requests more than 15 minutes
+/// off from the current time and expired pre-signed URLs are rejected.
+///
+/// Authenticated requests are answered with the responses registered with
+/// [MockS3Server::respond] or, for path-style object URLs, from an in-memory
+/// store: `PUT`, `GET` (with a single `Range`), `HEAD` and `DELETE` of objects
+/// and `ListObjectsV2`. Other requests receive a `NotImplemented` error.
+///
+/// The server stops when dropped.
+///
+/// ```
+/// # fn main() -> s3v4::Result<()> {
+/// use s3v4::test_util::MockS3Server;
+/// let server = MockS3Server::start(s3v4::Credentials::new("access", "secret"), s3v4::Region::UsEast1)?;
+/// server.put_object("bucket", "key", b"data");
+/// let url = server.endpoint().join("bucket/key").unwrap();
+/// let presigned = s3v4::pre_signed_url(
+///     "access", "secret", 60, &url, &s3v4::Method::Get, "UNSIGNED-PAYLOAD",
+///     &s3v4::Region::UsEast1, &chrono::Utc::now(), "s3",
+/// )?;
+/// assert!(presigned.starts_with(server.endpoint().as_str()));
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockS3Server {
+    endpoint: Url,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// State shared with the connection threads.
+struct State {
+    credentials: Vec<Credentials>,
+    region: Region,
+    objects: BTreeMap<(String, String), StoredObject>,
+    responses: Vec<(Method, String, MockResponse)>,
+    /// Responses used once, before `responses`.
+    once: Vec<(Method, String, MockResponse)>,
+    requests: Vec<RecordedRequest>,
+}
+
+struct StoredObject {
+    data: Vec<u8>,
+    etag: String,
+    last_modified: DateTime<Utc>,
+    /// `content-type` and `x-amz-meta-*` headers.
+    headers: Vec<(String, String)>,
+}
+
+impl MockS3Server {
+    /// Start a server on a free port of `127.0.0.1`, accepting requests signed
+    /// with `credentials` for `region`.
+    pub fn start(credentials: Credentials, region: Region) -> Result<Self> {
+        let listener =
+            TcpListener::bind("127.0.0.1:0").chain_err(|| "Error binding mock server")?;
+        let addr = listener
+            .local_addr()
+            .chain_err(|| "Error binding mock server")?;
+        let state = Arc::new(Mutex::new(State {
+            credentials: vec![credentials],
+            region,
+            objects: BTreeMap::new(),
+            responses: Vec::new(),
+            once: Vec::new(),
+            requests: Vec::new(),
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (state, shutdown) = (state.clone(), shutdown.clone());
+            std::thread::spawn(move || accept(listener, state, shutdown))
+        };
+        Ok(MockS3Server {
+            endpoint: Url::parse(&format!("http://{}/", addr)).chain_err(|| "Invalid address")?,
+            state,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// Return the URL of the server, e.g. `http://127.0.0.1:34567/`; objects
+    /// are accessed with path-style URLs.
+    pub fn endpoint(&self) -> Url {
+        self.endpoint.clone()
+    }
+
+    /// Accept requests signed with `credentials` as well, e.g. rotated
+    /// credentials; a session token, if any, must be sent with the requests.
+    pub fn add_credentials(&self, credentials: Credentials) {
+        self.state().credentials.push(credentials);
+    }
+
+    /// Store an object.
+    pub fn put_object(&self, bucket: &str, key: &str, data: &[u8]) {
+        self.state().objects.insert(
+            (bucket.to_string(), key.to_string()),
+            stored_object(data, Vec::new()),
+        );
+    }
+
+    /// Return the content of a stored object.
+    pub fn object(&self, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        self.state()
+            .objects
+            .get(&(bucket.to_string(), key.to_string()))
+            .map(|object| object.data.clone())
+    }
+
+    /// Answer authenticated `method` requests to `path` (e.g. `/bucket/key`,
+    /// without query) with `response` instead of the object store; later
+    /// responses for the same request replace the earlier ones.
+    pub fn respond(&self, method: Method, path: &str, response: MockResponse) {
+        let mut state = self.state();
+        state
+            .responses
+            .retain(|(m, p, _)| *m != method || p != path);
+        state.responses.push((method, path.to_string(), response));
+    }
+
+    /// Answer the next authenticated `method` request to `path` with
+    /// `response`, e.g. to simulate a transient error; responses registered
+    /// several times for the same request are used in order.
+    pub fn respond_once(&self, method: Method, path: &str, response: MockResponse) {
+        self.state().once.push((method, path.to_string(), response));
+    }
+
+    /// Return the requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockS3Server {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // wake up the accepting thread
+        let addr = self.endpoint.socket_addrs(|| None).ok();
+        if let Some(addr) = addr.as_ref().and_then(|addrs| addrs.first()) {
+            let _ = TcpStream::connect::<SocketAddr>(*addr);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+/// Accept connections until `shutdown` is set, serving each in a thread.
+fn accept(listener: TcpListener, state: Arc<Mutex<State>>, shutdown: Arc<AtomicBool>) {
+    for stream in listener.incoming() {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        if let Ok(stream) = stream {
+            let state = state.clone();
+            std::thread::spawn(move || serve(stream, &state));
+        }
+    }
+}
+
+/// Serve the requests of a persistent connection until it is closed.
+fn serve(stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let mut parts = line.split_whitespace();
+        let (method, target) = match (parts.next(), parts.next()) {
+            (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+            _ => return Ok(()),
+        };
+        let mut headers: Vec<(String, String)> = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+            }
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.to_lowercase())
+        };
+        if header("expect").as_deref() == Some("100-continue") {
+            writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+        let body = if header("transfer-encoding").as_deref() == Some("chunked") {
+            read_chunked(&mut reader)?
+        } else {
+            let len = header("content-length")
+                .and_then(|len| len.parse::<usize>().ok())
+                .unwrap_or(0);
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body)?;
+            body
+        };
+        let close = header("connection").as_deref() == Some("close");
+        let response = handle(state, &method, &target, headers, body);
+        write_response(&mut writer, &response, method == "HEAD")?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+/// Read a body sent with the chunked transfer encoding.
+fn read_chunked(reader: &mut impl BufRead) -> std::io::Result<Vec<u8>> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid chunk size");
+    let mut body = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16).map_err(|_| invalid())?;
+        if size == 0 {
+            // trailers
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        let mut crlf = [0; 2];
+        reader.read_exact(&mut crlf)?;
+    }
+}
+
+fn write_response(
+    writer: &mut impl Write,
+    response: &MockResponse,
+    head: bool,
+) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        204 => "No Content",
+        206 => "Partial Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        412 => "Precondition Failed",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let mut head_lines = format!("HTTP/1.1 {} {}\r\n", response.status, reason);
+    for (name, value) in &response.headers {
+        head_lines.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if response.header("content-length").is_none() {
+        head_lines.push_str(&format!("content-length: {}\r\n", response.body.len()));
+    }
+    head_lines.push_str("\r\n");
+    writer.write_all(head_lines.as_bytes())?;
+    if !head {
+        writer.write_all(&response.body)?;
+    }
+    writer.flush()
+}
+
+// -----------------------------------------------------------------------------
+/// Verify and answer a request, recording it.
+fn handle(
+    state: &Mutex<State>,
+    method: &str,
+    target: &str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> MockResponse {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    let method = match method.parse::<Method>() {
+        Ok(method) => method,
+        Err(_) => {
+            return MockResponse::error(
+                501,
+                "NotImplemented",
+                "A header you provided implies functionality that is not implemented.",
+            )
+        }
+    };
+    // requests forwarded by a proxy are signed for the host the client sent
+    // them to: verify them as if received with that `host` header
+    let host = ["x-forwarded-host", "host"]
+        .iter()
+        .find_map(|name| headers.iter().find(|(k, _)| k == name))
+        .and_then(|(_, v)| v.split(',').next())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default();
+    let verified_headers = headers
+        .iter()
+        .map(|(k, v)| match k.as_str() {
+            "host" => (k.clone(), host.clone()),
+            _ => (k.clone(), v.clone()),
+        })
+        .collect::<Vec<_>>();
+    let url = match Url::parse(&format!("http://{}{}", host, target)) {
+        Ok(url) => url,
+        Err(_) => {
+            return MockResponse::error(400, "InvalidURI", "Couldn't parse the specified URI.")
+        }
+    };
+    let (access, response) = match verify(&state, &method, &url, &verified_headers, &body) {
+        Ok(access) => {
+            let once = state
+                .once
+                .iter()
+                .position(|(m, p, _)| *m == method && p == url.path());
+            let response = match once {
+                Some(index) => state.once.remove(index).2,
+                None => match state
+                    .responses
+                    .iter()
+                    .find(|(m, p, _)| *m == method && p == url.path())
+                {
+                    Some((_, _, response)) => response.clone(),
+                    None => store_response(&mut state, &method, &url, &headers, &body),
+                },
+            };
+            (Some(access), response)
+        }
+        Err(response) => (None, response),
+    };
+    state.requests.push(RecordedRequest {
+        method,
+        url,
+        headers,
+        body,
+        access,
+        status: response.status,
+    });
+    response
+}
+
+/// Signature parameters of a request.
+struct Authorization {
+    credential: String,
+    signed_headers: String,
+    signature: String,
+    timestamp: String,
+    /// `X-Amz-Expires` of pre-signed URLs, `None` for header-signed requests.
+    expires: Option<i64>,
+    payload_hash: String,
+    session_token: Option<String>,
+}
+
+/// Verify the signature of a request, returning the access key or the error
+/// response to send.
+fn verify(
+    state: &State,
+    method: &Method,
+    url: &Url,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> std::result::Result<String, MockResponse> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.clone())
+    };
+    let malformed =
+        |message: &str| MockResponse::error(400, "AuthorizationHeaderMalformed", message);
+    let query = url.query_pairs().into_owned().collect::<BTreeMap<_, _>>();
+    let auth = if let Some(authorization) = header("authorization") {
+        let fields = match authorization.strip_prefix(ALGORITHM) {
+            Some(fields) => fields
+                .split(',')
+                .filter_map(|field| field.trim().split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            None => {
+                return Err(MockResponse::error(
+                    400,
+                    "InvalidArgument",
+                    "Unsupported Authorization Type",
+                ))
+            }
+        };
+        let timestamp = match (header("x-amz-date"), header("date")) {
+            (Some(timestamp), _) => timestamp,
+            (None, Some(date)) => match DateTime::parse_from_rfc2822(&date) {
+                Ok(date) => date.format(LONG_DATETIME_FMT).to_string(),
+                Err(_) => return Err(malformed("Invalid Date header")),
+            },
+            (None, None) => {
+                return Err(MockResponse::error(
+                    403,
+                    "AccessDenied",
+                    "AWS authentication requires a valid Date or x-amz-date header",
+                ))
+            }
+        };
+        let payload_hash = match header("x-amz-content-sha256") {
+            Some(hash) => hash,
+            None => {
+                return Err(MockResponse::error(
+                    400,
+                    "InvalidRequest",
+                    "Missing required header for this request: x-amz-content-sha256",
+                ))
+            }
+        };
+        match (
+            fields.get("Credential"),
+            fields.get("SignedHeaders"),
+            fields.get("Signature"),
+        ) {
+            (Some(credential), Some(signed_headers), Some(signature)) => Authorization {
+                credential: credential.clone(),
+                signed_headers: signed_headers.clone(),
+                signature: signature.clone(),
+                timestamp,
+                expires: None,
+                payload_hash,
+                session_token: header(SECURITY_TOKEN_HEADER),
+            },
+            _ => return Err(malformed("The authorization header is malformed")),
+        }
+    } else if let Some(signature) = query.get("X-Amz-Signature") {
+        let param = |name: &str| {
+            query
+                .get(name)
+                .cloned()
+                .ok_or_else(|| MockResponse::error(400, "AuthorizationQueryParametersError", name))
+        };
+        if param("X-Amz-Algorithm")? != ALGORITHM {
+            return Err(MockResponse::error(
+                400,
+                "AuthorizationQueryParametersError",
+                "X-Amz-Algorithm only supports \"AWS4-HMAC-SHA256\"",
+            ));
+        }
+        let expires = match param("X-Amz-Expires")?.parse::<i64>() {
+            Ok(expires) if (0..=MAX_EXPIRES).contains(&expires) => expires,
+            _ => {
+                return Err(MockResponse::error(
+                    400,
+                    "AuthorizationQueryParametersError",
+                    "X-Amz-Expires must be less than a week (in seconds) that is 604800",
+                ))
+            }
+        };
+        Authorization {
+            credential: param("X-Amz-Credential")?,
+            signed_headers: param("X-Amz-SignedHeaders")?,
+            signature: signature.clone(),
+            timestamp: param("X-Amz-Date")?,
+            expires: Some(expires),
+            payload_hash: UNSIGNED_PAYLOAD.to_string(),
+            session_token: query.get("X-Amz-Security-Token").cloned(),
+        }
+    } else {
+        return Err(MockResponse::error(403, "AccessDenied", "Access Denied"));
+    };
+
+    // credential scope: access/date/region/service/aws4_request
+    let scope = auth.credential.splitn(5, '/').collect::<Vec<_>>();
+    if scope.len() != 5 || scope[4] != "aws4_request" {
+        return Err(malformed("The credential is malformed"));
+    }
+    let (access, region, service) = (scope[0], scope[2], scope[3]);
+    if region != state.region.as_str() {
+        return Err(malformed(&format!(
+            "the region '{}' is wrong; expecting '{}'",
+            region, state.region
+        )));
+    }
+    if service != "s3" {
+        return Err(malformed(&format!(
+            "the service '{}' is wrong; expecting 's3'",
+            service
+        )));
+    }
+    let credentials = match state.credentials.iter().find(|c| c.access == access) {
+        Some(credentials) => credentials,
+        None => {
+            return Err(MockResponse::error(
+                403,
+                "InvalidAccessKeyId",
+                "The AWS Access Key Id you provided does not exist in our records.",
+            ))
+        }
+    };
+    if credentials.session_token.is_some() && auth.session_token != credentials.session_token {
+        return Err(MockResponse::error(
+            400,
+            "InvalidToken",
+            "The provided token is malformed or otherwise invalid.",
+        ));
+    }
+    let date_time = match NaiveDateTime::parse_from_str(&auth.timestamp, LONG_DATETIME_FMT) {
+        Ok(date_time) => Utc.from_utc_datetime(&date_time),
+        Err(_) => return Err(malformed("Invalid X-Amz-Date")),
+    };
+    let now = Utc::now();
+    match auth.expires {
+        Some(expires) if date_time + chrono::Duration::seconds(expires) < now => {
+            return Err(MockResponse::error(
+                403,
+                "AccessDenied",
+                "Request has expired",
+            ))
+        }
+        None if (now - date_time).num_seconds().abs() > MAX_SKEW => {
+            return Err(MockResponse::error(
+                403,
+                "RequestTimeTooSkewed",
+                "The difference between the request time and the current time is too large.",
+            ))
+        }
+        _ => {}
+    }
+    if auth.payload_hash != UNSIGNED_PAYLOAD
+        && auth.payload_hash != hex::encode(Crypto::sha256(body))
+    {
+        return Err(MockResponse::error(
+            400,
+            "XAmzContentSHA256Mismatch",
+            "The provided 'x-amz-content-sha256' header does not match what was computed.",
+        ));
+    }
+
+    let canonical = reference_canonical_request(
+        method,
+        url,
+        headers,
+        &auth.signed_headers,
+        &auth.payload_hash,
+    )
+    .map_err(|name| malformed(&format!("Missing signed header '{}'", name)))?;
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        auth.timestamp,
+        scope[1..].join("/"),
+        hex::encode(Crypto::sha256(canonical.as_bytes()))
+    );
+    let signature = reference_signature(&credentials.secret, &scope[1..4], &string_to_sign);
+    if signature != auth.signature {
+        return Err(error_response(
+            403,
+            "SignatureDoesNotMatch",
+            "The request signature we calculated does not match the signature you provided. \
+             Check your key and signing method.",
+            &[
+                ("AWSAccessKeyId", access),
+                ("StringToSign", &string_to_sign),
+                ("SignatureProvided", &auth.signature),
+                ("CanonicalRequest", &canonical),
+            ],
+        ));
+    }
+    Ok(access.to_string())
+}
+
+// -----------------------------------------------------------------------------
+// Reference implementation of the canonical request and signature, written from
+// the AWS documentation independently of the signing code of the crate, so that
+// the mock server does not accept requests signed with the same mistakes.
+
+/// Return the canonical request of a received request, excluding the
+/// `X-Amz-Signature` of pre-signed URLs, or the name of a missing signed header.
+fn reference_canonical_request(
+    method: &Method,
+    url: &Url,
+    headers: &[(String, String)],
+    signed_headers: &str,
+    payload_hash: &str,
+) -> std::result::Result<String, String> {
+    let path = url
+        .path()
+        .split('/')
+        .map(|segment| reference_encode(&reference_decode(segment)))
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut query = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .filter(|(k, _)| *k != "X-Amz-Signature")
+        .map(|(k, v)| (query_component(k), query_component(v)))
+        .collect::<Vec<_>>();
+    query.sort();
+    let query = query
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut canonical_headers = String::new();
+    for name in signed_headers.split(';') {
+        // values of repeated headers are joined with commas
+        let values = headers
+            .iter()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            return Err(name.to_string());
+        }
+        canonical_headers += &format!("{}:{}\n", name, values.join(","));
+    }
+    Ok(format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.as_str(),
+        path,
+        query,
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    ))
+}
+
+/// Return the hex-encoded signature of `string_to_sign` for the `date`,
+/// `region` and `service` of `scope`.
+fn reference_signature(secret: &str, scope: &[&str], string_to_sign: &str) -> String {
+    let mut key = format!("AWS4{}", secret).into_bytes();
+    for part in scope.iter().chain(&["aws4_request"]) {
+        key = Crypto::hmac_sha256(&key, part.as_bytes()).to_vec();
+    }
+    hex::encode(Crypto::hmac_sha256(&key, string_to_sign.as_bytes()))
+}
+
+/// Percent-encode all bytes but the unreserved characters `A-Z a-z 0-9 - . _ ~`.
+fn reference_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Percent-decode a path segment.
+fn reference_decode(s: &str) -> String {
+    String::from_utf8_lossy(&urlencoding::decode_binary(s.as_bytes())).into_owned()
+}
+
+/// Canonicalize a query key or value; like S3, `+` is decoded as a space, so
+/// that clients must send it as `%2B`.
+fn query_component(s: &str) -> String {
+    reference_encode(&reference_decode(&s.replace('+', " ")))
+}
+
+// -----------------------------------------------------------------------------
+/// Answer a request from the object store.
+fn store_response(
+    state: &mut State,
+    method: &Method,
+    url: &Url,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> MockResponse {
+    let not_implemented = || {
+        MockResponse::error(
+            501,
+            "NotImplemented",
+            "A header or query you provided implies functionality that is not implemented.",
+        )
+    };
+    let path = urlencoding::decode(url.path())
+        .map(|path| path.into_owned())
+        .unwrap_or_default();
+    let (bucket, key) = match path.trim_start_matches('/').split_once('/') {
+        Some((bucket, key)) => (bucket.to_string(), key.to_string()),
+        None => (path.trim_start_matches('/').to_string(), String::new()),
+    };
+    let query = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(k, _)| !k.starts_with("X-Amz-"))
+        .collect::<BTreeMap<_, _>>();
+    if bucket.is_empty() {
+        return not_implemented();
+    }
+    if key.is_empty() {
+        return match method {
+            Method::Get if query.get("list-type").map(String::as_str) == Some("2") => {
+                list_objects(state, &bucket, &query)
+            }
+            Method::Put | Method::Head if query.is_empty() => MockResponse::new(200, ""),
+            _ => not_implemented(),
+        };
+    }
+    if !query.is_empty() {
+        return not_implemented();
+    }
+    let id = (bucket, key);
+    match method {
+        Method::Put => {
+            let metadata = headers
+                .iter()
+                .filter(|(k, _)| k == "content-type" || k.starts_with("x-amz-meta-"))
+                .cloned()
+                .collect();
+            let object = stored_object(body, metadata);
+            let etag = object.etag.clone();
+            state.objects.insert(id, object);
+            MockResponse::new(200, "").with_header("etag", &etag)
+        }
+        Method::Get | Method::Head => match state.objects.get(&id) {
+            Some(object) if !etag_matches(object, headers) => {
+                if *method == Method::Head {
+                    MockResponse::new(412, "")
+                } else {
+                    error_response(
+                        412,
+                        "PreconditionFailed",
+                        "At least one of the pre-conditions you specified did not hold",
+                        &[("Condition", "If-Match")],
+                    )
+                }
+            }
+            Some(object) => {
+                let range = headers
+                    .iter()
+                    .find(|(k, _)| k == "range")
+                    .map(|(_, v)| v.as_str());
+                object_response(object, range, *method == Method::Head)
+            }
+            None if *method == Method::Head => MockResponse::new(404, ""),
+            None => error_response(
+                404,
+                "NoSuchKey",
+                "The specified key does not exist.",
+                &[("Key", &id.1)],
+            ),
+        },
+        Method::Delete => {
+            state.objects.remove(&id);
+            MockResponse::new(204, "")
+        }
+        _ => not_implemented(),
+    }
+}
+
+/// Return `false` if the `If-Match` header of a request does not match the
+/// ETag of `object`.
+fn etag_matches(object: &StoredObject, headers: &[(String, String)]) -> bool {
+    match headers.iter().find(|(k, _)| k == "if-match") {
+        Some((_, value)) => value
+            .split(',')
+            .any(|etag| etag.trim() == "*" || etag.trim() == object.etag),
+        None => true,
+    }
+}
+
+fn stored_object(data: &[u8], headers: Vec<(String, String)>) -> StoredObject {
+    StoredObject {
+        data: data.to_vec(),
+        etag: format!("\"{}\"", hex::encode(Md5::digest(data))),
+        last_modified: Utc::now(),
+        headers,
+    }
+}
+
+/// Return the response to a `GET` or `HEAD` request.
+fn object_response(object: &StoredObject, range: Option<&str>, head: bool) -> MockResponse {
+    let len = object.data.len();
+    let mut response = MockResponse::new(200, "")
+        .with_header("etag", &object.etag)
+        .with_header(
+            "last-modified",
+            &object.last_modified.format(HTTP_DATE_FMT).to_string(),
+        )
+        .with_header("accept-ranges", "bytes");
+    if !object.headers.iter().any(|(k, _)| k == "content-type") {
+        response = response.with_header("content-type", "binary/octet-stream");
+    }
+    response.headers.extend(object.headers.iter().cloned());
+    let (start, end) = match range.and_then(|range| parse_range(range, len)) {
+        Some(Ok((start, end))) => {
+            response.status = 206;
+            response = response.with_header(
+                "content-range",
+                &format!("bytes {}-{}/{}", start, end - 1, len),
+            );
+            (start, end)
+        }
+        Some(Err(())) => {
+            return error_response(
+                416,
+                "InvalidRange",
+                "The requested range is not satisfiable",
+                &[],
+            )
+        }
+        None => (0, len),
+    };
+    if head {
+        response.with_header("content-length", &(end - start).to_string())
+    } else {
+        response.body = object.data[start..end].to_vec();
+        response
+    }
+}
+
+/// Parse a single `bytes=` range into the start and end (exclusive) offsets;
+/// `None` if the header is not supported, in which case the whole object is
+/// returned, and `Err` if the range is not satisfiable.
+fn parse_range(range: &str, len: usize) -> Option<std::result::Result<(usize, usize), ()>> {
+    let (first, last) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let range = match (first.parse::<usize>(), last.parse::<usize>()) {
+        (Ok(first), Ok(last)) if first <= last => (first, last.saturating_add(1).min(len)),
+        (Ok(first), Err(_)) if last.is_empty() => (first, len),
+        (Err(_), Ok(suffix)) if first.is_empty() => (len.saturating_sub(suffix), len),
+        _ => return None,
+    };
+    if range.0 >= len {
+        Some(Err(()))
+    } else {
+        Some(Ok(range))
+    }
+}
+
+/// Return a `ListObjectsV2` page, using the last key as continuation token.
+fn list_objects(state: &State, bucket: &str, query: &BTreeMap<String, String>) -> MockResponse {
+    let prefix = query.get("prefix").cloned().unwrap_or_default();
+    let max_keys = query
+        .get("max-keys")
+        .and_then(|max| max.parse::<usize>().ok())
+        .unwrap_or(1000);
+    let after = query
+        .get("continuation-token")
+        .or_else(|| query.get("start-after"));
+    let mut objects = state
+        .objects
+        .iter()
+        .filter(|((b, key), _)| {
+            b == bucket && key.starts_with(&prefix) && after.map_or(true, |after| key > after)
+        })
+        .map(|((_, key), object)| (key, object))
+        .peekable();
+    let mut contents = String::new();
+    let mut count = 0;
+    let mut last = None;
+    while count < max_keys {
+        let (key, object) = match objects.next() {
+            Some(object) => object,
+            None => break,
+        };
+        contents.push_str(&format!(
+            "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>{}</ETag>\
+             <Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+            xml::escape(key),
+            object.last_modified.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            xml::escape(&object.etag),
+            object.data.len()
+        ));
+        count += 1;
+        last = Some(key);
+    }
+    let truncated = objects.peek().is_some();
+    let next = match last {
+        Some(last) if truncated => format!(
+            "<NextContinuationToken>{}</NextContinuationToken>",
+            xml::escape(last)
+        ),
+        _ => String::new(),
+    };
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+         <Name>{}</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount><MaxKeys>{}</MaxKeys>\
+         <IsTruncated>{}</IsTruncated>{}{}</ListBucketResult>",
+        xml::escape(bucket),
+        xml::escape(&prefix),
+        count,
+        max_keys,
+        truncated,
+        next,
+        contents
+    );
+    MockResponse::new(200, &body).with_header("content-type", "application/xml")
+}
+
+/// Return an S3 error response with additional elements.
+fn error_response(
+    status: u16,
+    code: &str,
+    message: &str,
+    elements: &[(&str, &str)],
+) -> MockResponse {
+    let mut body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>{}</Code><Message>{}</Message>",
+        code,
+        xml::escape(message)
+    );
+    for (name, value) in elements {
+        body.push_str(&format!("<{0}>{1}</{0}>", name, xml::escape(value)));
+    }
+    body.push_str("</Error>");
+    MockResponse::new(status, &body).with_header("content-type", "application/xml")
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pre_signed_url, sign, signature_with_options, SigningOptions};
+
+    /// Send a request signed with `secret` and return the status and body.
+    fn send(
+        server: &MockS3Server,
+        method: Method,
+        path: &str,
+        secret: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<(u16, String)> {
+        let url = server.endpoint().join(path).chain_err(|| "Invalid URL")?;
+        let mut options = SigningOptions::default();
+        for (name, value) in headers {
+            options.headers.insert(name.to_string(), value.to_string());
+        }
+        let payload_hash = hex::encode(Crypto::sha256(body));
+        let signature = signature_with_options(
+            &url,
+            &method,
+            "access",
+            secret,
+            &Region::UsEast1,
+            "s3",
+            &payload_hash,
+            &options,
+        )?;
+        let headers = vec![
+            ("x-amz-content-sha256".to_string(), payload_hash),
+            ("x-amz-date".to_string(), signature.date_time),
+            ("authorization".to_string(), signature.auth_header),
+        ];
+        send_raw(
+            &url,
+            &method,
+            headers.into_iter().chain(options.headers).collect(),
+            body,
+        )
+    }
+
+    fn send_raw(
+        url: &Url,
+        method: &Method,
+        headers: Vec<(String, String)>,
+        body: &[u8],
+    ) -> Result<(u16, String)> {
+        let host = format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default(),
+            url.port().unwrap_or(80)
+        );
+        let mut stream = TcpStream::connect(&host).chain_err(|| "Error connecting")?;
+        let mut request = format!(
+            "{} {}{} HTTP/1.1\r\nhost: {}\r\nconnection: close\r\ncontent-length: {}\r\n",
+            method,
+            url.path(),
+            url.query().map(|q| format!("?{}", q)).unwrap_or_default(),
+            host,
+            body.len()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream
+            .write_all(request.as_bytes())
+            .and_then(|_| stream.write_all(body))
+            .chain_err(|| "Error sending request")?;
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .chain_err(|| "Error reading response")?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .chain_err(|| "Invalid response")?;
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .chain_err(|| "Invalid status line")?;
+        Ok((status, body.to_string()))
+    }
+
+    fn start() -> Result<MockS3Server> {
+        MockS3Server::start(Credentials::new("access", "secret"), Region::UsEast1)
+    }
+
+    #[test]
+    fn test_object_store() -> Result<()> {
+        let server = start()?;
+        let meta = [("x-amz-meta-author", "me")];
+        assert_eq!(
+            200,
+            send(
+                &server,
+                Method::Put,
+                "/b/my%20key",
+                "secret",
+                &meta,
+                b"hello"
+            )?
+            .0
+        );
+        assert_eq!(Some(b"hello".to_vec()), server.object("b", "my key"));
+        assert_eq!(
+            (200, "hello".to_string()),
+            send(&server, Method::Get, "/b/my%20key", "secret", &[], b"")?
+        );
+        assert_eq!(
+            (206, "el".to_string()),
+            send(
+                &server,
+                Method::Get,
+                "/b/my%20key",
+                "secret",
+                &[("range", "bytes=1-2")],
+                b""
+            )?
+        );
+        assert_eq!(
+            200,
+            send(
+                &server,
+                Method::Get,
+                "/b/my%20key",
+                "secret",
+                &[("if-match", "*")],
+                b""
+            )?
+            .0
+        );
+        let (status, body) = send(
+            &server,
+            Method::Get,
+            "/b/my%20key",
+            "secret",
+            &[("if-match", "\"other\"")],
+            b"",
+        )?;
+        assert_eq!(412, status);
+        assert_eq!(
+            Some("PreconditionFailed".to_string()),
+            xml::text(&body, "Code")
+        );
+        server.put_object("b", "other", b"");
+        let (status, list) = send(
+            &server,
+            Method::Get,
+            "/b?list-type=2&max-keys=1",
+            "secret",
+            &[],
+            b"",
+        )?;
+        assert_eq!(200, status);
+        assert_eq!(vec!["my key"], xml::elements(&list, "Key"));
+        assert_eq!(Some("true"), xml::element(&list, "IsTruncated"));
+        let (_, list) = send(
+            &server,
+            Method::Get,
+            "/b?list-type=2&continuation-token=my%20key",
+            "secret",
+            &[],
+            b"",
+        )?;
+        assert_eq!(vec!["other"], xml::elements(&list, "Key"));
+        assert_eq!(
+            204,
+            send(&server, Method::Delete, "/b/my%20key", "secret", &[], b"")?.0
+        );
+        let (status, body) = send(&server, Method::Get, "/b/my%20key", "secret", &[], b"")?;
+        assert_eq!(404, status);
+        assert_eq!(Some("NoSuchKey".to_string()), xml::text(&body, "Code"));
+        let requests = server.requests();
+        assert_eq!(9, requests.len());
+        assert_eq!(Some("access".to_string()), requests[0].access);
+        assert_eq!(b"hello".to_vec(), requests[0].body);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejected_requests() -> Result<()> {
+        let server = start()?;
+        let (status, body) = send(&server, Method::Get, "/b/key", "wrong", &[], b"")?;
+        assert_eq!(403, status);
+        assert_eq!(
+            Some("SignatureDoesNotMatch".to_string()),
+            xml::text(&body, "Code")
+        );
+        assert!(xml::text(&body, "CanonicalRequest")
+            .unwrap()
+            .starts_with("GET\n/b/key\n\nhost:127.0.0.1:"));
+        assert!(server.requests()[0].access.is_none());
+        let url = server
+            .endpoint()
+            .join("/b/key")
+            .chain_err(|| "Invalid URL")?;
+        let (status, body) = send_raw(&url, &Method::Get, Vec::new(), b"")?;
+        assert_eq!(403, status);
+        assert_eq!(Some("AccessDenied".to_string()), xml::text(&body, "Code"));
+        // payload different from the signed one
+        let signature = signature_with_options(
+            &url,
+            &Method::Put,
+            "access",
+            "secret",
+            &Region::UsEast1,
+            "s3",
+            &hex::encode(Crypto::sha256(b"signed")),
+            &SigningOptions::default(),
+        )?;
+        let headers = vec![
+            (
+                "x-amz-content-sha256".to_string(),
+                hex::encode(Crypto::sha256(b"signed")),
+            ),
+            ("x-amz-date".to_string(), signature.date_time),
+            ("authorization".to_string(), signature.auth_header),
+        ];
+        let (status, body) = send_raw(&url, &Method::Put, headers, b"sent")?;
+        assert_eq!(400, status);
+        assert_eq!(
+            Some("XAmzContentSHA256Mismatch".to_string()),
+            xml::text(&body, "Code")
+        );
+        // header-signed more than 15 minutes ago
+        let date_time = Utc::now() - chrono::Duration::minutes(20);
+        let mut headers = BTreeMap::new();
+        headers.insert(
+            "host".to_string(),
+            url[url::Position::BeforeHost..url::Position::AfterPort].to_string(),
+        );
+        headers.insert(
+            "x-amz-content-sha256".to_string(),
+            UNSIGNED_PAYLOAD.to_string(),
+        );
+        headers.insert(
+            "x-amz-date".to_string(),
+            date_time.format(LONG_DATETIME_FMT).to_string(),
+        );
+        let output = sign(
+            &Method::Get,
+            UNSIGNED_PAYLOAD,
+            url.as_str(),
+            &headers,
+            &date_time,
+            "secret",
+            &Region::UsEast1,
+            "s3",
+            &SigningOptions::default(),
+        )?;
+        headers.remove("host");
+        headers.insert(
+            "authorization".to_string(),
+            output.authorization_header("access"),
+        );
+        let (status, body) = send_raw(&url, &Method::Get, headers.into_iter().collect(), b"")?;
+        assert_eq!(403, status);
+        assert_eq!(
+            Some("RequestTimeTooSkewed".to_string()),
+            xml::text(&body, "Code")
+        );
+        // expired pre-signed URL
+        let presigned = pre_signed_url(
+            "access",
+            "secret",
+            60,
+            &url,
+            &Method::Get,
+            UNSIGNED_PAYLOAD,
+            &Region::UsEast1,
+            &(Utc::now() - chrono::Duration::minutes(2)),
+            "s3",
+        )?;
+        let presigned = Url::parse(&presigned).chain_err(|| "Invalid URL")?;
+        let (status, body) = send_raw(&presigned, &Method::Get, Vec::new(), b"")?;
+        assert_eq!(403, status);
+        assert_eq!(
+            Some("Request has expired".to_string()),
+            xml::text(&body, "Message")
+        );
+        // invalid method
+        let (status, _) = send_raw(&url, &Method::Custom("G\"ET".to_string()), Vec::new(), b"")?;
+        assert_eq!(501, status);
+        // wrong region
+        let server = MockS3Server::start(Credentials::new("access", "secret"), Region::EuWest1)?;
+        let (status, body) = send(&server, Method::Get, "/b/key", "secret", &[], b"")?;
+        assert_eq!(400, status);
+        assert!(xml::text(&body, "Message")
+            .unwrap()
+            .contains("'us-east-1' is wrong"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_forwarded_host() -> Result<()> {
+        let server = start()?;
+        server.put_object("b", "key", b"data");
+        let options = SigningOptions {
+            host_override: Some("s3.example.com".to_string()),
+            ..Default::default()
+        };
+        let url = server
+            .endpoint()
+            .join("/b/key")
+            .chain_err(|| "Invalid URL")?;
+        let signature = signature_with_options(
+            &url,
+            &Method::Get,
+            "access",
+            "secret",
+            &Region::UsEast1,
+            "s3",
+            UNSIGNED_PAYLOAD,
+            &options,
+        )?;
+        let headers = vec![
+            (
+                "x-amz-content-sha256".to_string(),
+                UNSIGNED_PAYLOAD.to_string(),
+            ),
+            ("x-amz-date".to_string(), signature.date_time),
+            ("authorization".to_string(), signature.auth_header),
+        ];
+        // signed for the public host, rejected without the proxy header
+        assert_eq!(403, send_raw(&url, &Method::Get, headers.clone(), b"")?.0);
+        let mut forwarded = headers;
+        forwarded.push(("x-forwarded-host".to_string(), "s3.example.com".to_string()));
+        assert_eq!(
+            (200, "data".to_string()),
+            send_raw(&url, &Method::Get, forwarded, b"")?
+        );
+        assert_eq!(
+            "s3.example.com",
+            server.requests()[1].url.host_str().unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_signed_url() -> Result<()> {
+        let server = start()?;
+        server.put_object("b", "key", b"data");
+        let url = server
+            .endpoint()
+            .join("/b/key")
+            .chain_err(|| "Invalid URL")?;
+        let presigned = pre_signed_url(
+            "access",
+            "secret",
+            60,
+            &url,
+            &Method::Get,
+            UNSIGNED_PAYLOAD,
+            &Region::UsEast1,
+            &Utc::now(),
+            "s3",
+        )?;
+        let presigned = Url::parse(&presigned).chain_err(|| "Invalid URL")?;
+        assert_eq!(
+            (200, "data".to_string()),
+            send_raw(&presigned, &Method::Get, Vec::new(), b"")?
+        );
+        // signed for GET only
+        assert_eq!(
+            403,
+            send_raw(&presigned, &Method::Delete, Vec::new(), b"")?.0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_canned_response() -> Result<()> {
+        let server = start()?;
+        server.respond(
+            Method::Get,
+            "/b/key",
+            MockResponse::error(503, "SlowDown", "Please reduce your request rate."),
+        );
+        let (status, body) = send(
+            &server,
+            Method::Get,
+            "/b/key?versionId=1",
+            "secret",
+            &[],
+            b"",
+        )?;
+        assert_eq!(503, status);
+        assert_eq!(Some("SlowDown".to_string()), xml::text(&body, "Code"));
+        // one-off responses are used first, in order
+        server.respond_once(Method::Get, "/b/key", MockResponse::new(500, ""));
+        server.respond_once(Method::Get, "/b/key", MockResponse::new(200, "once"));
+        let get = || send(&server, Method::Get, "/b/key", "secret", &[], b"");
+        assert_eq!(500, get()?.0);
+        assert_eq!((200, "once".to_string()), get()?);
+        assert_eq!(503, get()?.0);
+        // the signature is verified before answering
+        assert_eq!(
+            403,
+            send(&server, Method::Get, "/b/key", "wrong", &[], b"")?.0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_signature() {
+        // requests of the AWS SigV4 test suite, signed for `service`
+        let unreserved = "-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+        let vectors = [
+            (
+                "get-vanilla",
+                Method::Get,
+                "/".to_string(),
+                &[][..],
+                "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+            ),
+            (
+                "post-vanilla",
+                Method::Post,
+                "/".to_string(),
+                &[],
+                "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b",
+            ),
+            (
+                "get-vanilla-query-order-key-case",
+                Method::Get,
+                "/?Param2=value2&Param1=value1".to_string(),
+                &[],
+                "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+            ),
+            (
+                "get-vanilla-query-order-value",
+                Method::Get,
+                "/?Param1=value2&Param1=Value1".to_string(),
+                &[],
+                "eedbc4e291e521cf13422ffca22be7d2eb8146eecf653089df300a15b2382bd1",
+            ),
+            (
+                "get-vanilla-query-unreserved",
+                Method::Get,
+                format!("/?{0}={0}", unreserved),
+                &[],
+                "9c3e54bfcdf0b19771a7f523ee5669cdf59bc7cc0884027167c21bb143a40197",
+            ),
+            (
+                "get-vanilla-utf8-query",
+                Method::Get,
+                "/?\u{1234}=bar".to_string(),
+                &[],
+                "2cdec8eed098649ff3a119c94853b13c643bcf08f8b0a1d91e12c9027818dd04",
+            ),
+            (
+                "get-space",
+                Method::Get,
+                "/example space/".to_string(),
+                &[],
+                "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741",
+            ),
+            (
+                "get-utf8",
+                Method::Get,
+                "/\u{1234}".to_string(),
+                &[],
+                "8318018e0b0f223aa2bbf98705b62bb787dc9c0e678f255a891fd03141be5d85",
+            ),
+            (
+                "get-header-value-trim",
+                Method::Get,
+                "/".to_string(),
+                &[("my-header1", "value1"), ("my-header2", "\"a   b   c\"")],
+                "acc3ed3afb60bb290fc8d2dd0098b9911fcaa05412b367055dee359757a9c736",
+            ),
+        ];
+        for (name, method, target, extra, expected) in vectors {
+            let url = Url::parse(&format!("https://example.amazonaws.com{}", target)).unwrap();
+            let mut headers = vec![
+                ("host".to_string(), "example.amazonaws.com".to_string()),
+                ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+            ];
+            headers.extend(extra.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+            headers.sort();
+            let signed = headers
+                .iter()
+                .map(|(k, _)| k.as_str())
+                .collect::<Vec<_>>()
+                .join(";");
+            let canonical = reference_canonical_request(
+                &method,
+                &url,
+                &headers,
+                &signed,
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            )
+            .unwrap();
+            let string_to_sign = format!(
+                "{}\n20150830T123600Z\n20150830/us-east-1/service/aws4_request\n{}",
+                ALGORITHM,
+                hex::encode(Crypto::sha256(canonical.as_bytes()))
+            );
+            let signature = reference_signature(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                &["20150830", "us-east-1", "service"],
+                &string_to_sign,
+            );
+            assert_eq!(expected, signature, "{}", name);
+        }
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        assert_eq!(
+            Err("x-amz-date".to_string()),
+            reference_canonical_request(&Method::Get, &url, &[], "x-amz-date", "")
+        );
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(Some(Ok((0, 10))), parse_range("bytes=0-", 10));
+        assert_eq!(Some(Ok((2, 5))), parse_range("bytes=2-4", 10));
+        assert_eq!(Some(Ok((8, 10))), parse_range("bytes=-2", 10));
+        assert_eq!(Some(Ok((8, 10))), parse_range("bytes=8-20", 10));
+        assert_eq!(Some(Err(())), parse_range("bytes=10-", 10));
+        assert_eq!(None, parse_range("bytes=0-1,3-4", 10));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_client() -> Result<()> {
+        let server = start()?;
+        let temporary = Credentials {
+            session_token: Some("token".to_string()),
+            ..Credentials::new("temporary", "secret")
+        };
+        server.add_credentials(temporary.clone());
+        for client in [
+            crate::client::Client::new(server.endpoint(), "access", "secret", Region::UsEast1),
+            crate::client::Client::new(server.endpoint(), "", "", Region::UsEast1)
+                .with_credentials(temporary),
+        ] {
+            let etag = client.put_object("b", "dir/key", b"data")?;
+            assert_eq!(format!("\"{}\"", hex::encode(Md5::digest(b"data"))), etag);
+            assert_eq!(4, client.content_length("b", "dir/key")?);
+            let mut data = Vec::new();
+            let verified = client.get_object_verified("b", "dir/key", &mut data)?;
+            assert_eq!(b"data".to_vec(), data);
+            assert_eq!(crate::client::ChecksumAlgorithm::Md5, verified.algorithm);
+            let objects = client.list_objects("b", "dir/")?;
+            assert_eq!(1, objects.len());
+            assert_eq!(4, objects[0].size);
+            client.delete_object("b", "dir/key", None)?;
+        }
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.access.is_some()));
+        Ok(())
+    }
+}
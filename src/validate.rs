@@ -0,0 +1,142 @@
+//! Validation of bucket names and object keys.
+//!
+//! [reference](https://docs.aws.amazon.com/AmazonS3/latest/userguide/bucketnamingrules.html)
+
+use crate::errors::*;
+use std::net::Ipv4Addr;
+
+const RESERVED_PREFIXES: &[&str] = &["xn--", "sthree-", "amzn-s3-demo-"];
+const RESERVED_SUFFIXES: &[&str] = &["-s3alias", "--ol-s3", ".mrap", "--x-s3", "--table-s3"];
+const MAX_KEY_LEN: usize = 1024;
+
+// -----------------------------------------------------------------------------
+/// Check that a bucket name follows the S3 naming rules:
+/// * 3 to 63 characters long
+/// * only lower case letters, digits, dots and hyphens
+/// * begins and ends with a letter or digit
+/// * no adjacent dots
+/// * not formatted as an IP address
+/// * no reserved prefix (e.g. `xn--`) or suffix (e.g. `-s3alias`)
+pub fn validate_bucket_name(name: &str) -> Result<()> {
+    if name.len() < 3 || name.len() > 63 {
+        bail!(
+            "Invalid bucket name '{}': must be 3 to 63 characters long",
+            name
+        );
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-')
+    {
+        bail!(
+            "Invalid bucket name '{}': only lower case letters, digits, '.' and '-' are allowed",
+            name
+        );
+    }
+    let alphanumeric = |c: Option<char>| c.map_or(false, |c| c.is_ascii_alphanumeric());
+    if !alphanumeric(name.chars().next()) || !alphanumeric(name.chars().last()) {
+        bail!(
+            "Invalid bucket name '{}': must begin and end with a letter or digit",
+            name
+        );
+    }
+    if name.contains("..") {
+        bail!("Invalid bucket name '{}': adjacent periods", name);
+    }
+    if name.parse::<Ipv4Addr>().is_ok() {
+        bail!("Invalid bucket name '{}': formatted as an IP address", name);
+    }
+    if let Some(p) = RESERVED_PREFIXES.iter().find(|p| name.starts_with(*p)) {
+        bail!("Invalid bucket name '{}': reserved prefix '{}'", name, p);
+    }
+    if let Some(s) = RESERVED_SUFFIXES.iter().find(|s| name.ends_with(*s)) {
+        bail!("Invalid bucket name '{}': reserved suffix '{}'", name, s);
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+/// Check that a bucket name can be used as part of the host name in
+/// virtual-hosted-style `https` URLs: it must be valid and must not contain dots,
+/// which would not match the wildcard TLS certificate of the endpoint.
+pub fn validate_virtual_host_bucket_name(name: &str) -> Result<()> {
+    validate_bucket_name(name)?;
+    if name.contains('.') {
+        bail!(
+            "Bucket name '{}' contains '.' and cannot be used with virtual-hosted-style HTTPS URLs",
+            name
+        );
+    }
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+/// Check that an object key is valid and can be addressed through a URL:
+/// * 1 to 1024 bytes long
+/// * no control characters
+/// * no `.` or `..` path segments, which would be removed when the URL is
+///   normalized
+pub fn validate_object_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        bail!(
+            "Invalid object key: must be 1 to {} bytes long, is {}",
+            MAX_KEY_LEN,
+            key.len()
+        );
+    }
+    if key.chars().any(|c| c.is_control()) {
+        bail!(
+            "Invalid object key '{}': contains control characters",
+            key.escape_default()
+        );
+    }
+    if key.split('/').any(|s| s == "." || s == "..") {
+        bail!(
+            "Invalid object key '{}': contains '.' or '..' segments",
+            key
+        );
+    }
+    Ok(())
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_bucket_name() {
+        for name in ["my-bucket", "bucket.example.com", "123", "a-1.b-2"] {
+            assert!(validate_bucket_name(name).is_ok(), "{}", name);
+        }
+        for name in [
+            "ab",
+            &"a".repeat(64),
+            "My-Bucket",
+            "my_bucket",
+            "-bucket",
+            "bucket-",
+            "my..bucket",
+            "192.168.5.4",
+            "xn--bucket",
+            "bucket-s3alias",
+            "bucket.mrap",
+        ] {
+            assert!(validate_bucket_name(name).is_err(), "{}", name);
+        }
+        assert!(validate_virtual_host_bucket_name("my-bucket").is_ok());
+        assert!(validate_virtual_host_bucket_name("my.bucket").is_err());
+    }
+
+    #[test]
+    fn test_validate_object_key() {
+        for key in ["key", "dir/sub dir/file.txt", "dir/", "ü/..hidden", "a.b/c"] {
+            assert!(validate_object_key(key).is_ok(), "{}", key);
+        }
+        let long = "k".repeat(1025);
+        for key in ["", &long, "a\nb", "a/../b", "./a", "a/."] {
+            assert!(validate_object_key(key).is_err(), "{}", key);
+        }
+    }
+}
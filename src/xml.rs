@@ -0,0 +1,143 @@
+//! Minimal XML helpers for the flat documents returned by S3.
+
+// only `escape` is used without the `client` and `event-stream` features
+#![cfg_attr(
+    not(any(feature = "client", feature = "event-stream")),
+    allow(dead_code)
+)]
+
+// -----------------------------------------------------------------------------
+/// Return the content of all the `<tag>` elements, in document order.
+/// Nested elements with the same name are not supported.
+pub(crate) fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut result = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        // skip elements whose name only starts with `tag`
+        match after.chars().next() {
+            Some('>') | Some(' ') | Some('/') => {}
+            _ => {
+                rest = after;
+                continue;
+            }
+        }
+        let gt = match after.find('>') {
+            Some(gt) => gt,
+            None => break,
+        };
+        if after[..gt].ends_with('/') {
+            // empty element: <tag/>
+            result.push("");
+            rest = &after[gt + 1..];
+            continue;
+        }
+        let content = &after[gt + 1..];
+        match content.find(&close) {
+            Some(end) => {
+                result.push(&content[..end]);
+                rest = &content[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    result
+}
+
+/// Return the content of the first `<tag>` element.
+pub(crate) fn element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    elements(xml, tag).into_iter().next()
+}
+
+/// Return the unescaped text of the first `<tag>` element.
+pub(crate) fn text(xml: &str, tag: &str) -> Option<String> {
+    element(xml, tag).map(unescape)
+}
+
+/// Replace the predefined XML entities and character references.
+pub(crate) fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let semi = match rest.find(';') {
+            Some(semi) => semi,
+            None => break,
+        };
+        let entity = &rest[1..semi];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Escape the characters which cannot appear in XML text.
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Unit tests
+//==============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elements() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListPartsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+<Part><PartNumber>1</PartNumber><ETag>&quot;a&quot;</ETag></Part>
+<Part><PartNumber>2</PartNumber><ETag>"b"</ETag></Part>
+<PartNumberMarker/><IsTruncated>false</IsTruncated>
+</ListPartsResult>"#;
+        let parts = elements(xml, "Part");
+        assert_eq!(2, parts.len());
+        assert_eq!(Some("1"), element(parts[0], "PartNumber"));
+        assert_eq!(Some("\"a\"".to_string()), text(parts[0], "ETag"));
+        assert_eq!(Some(""), element(xml, "PartNumberMarker"));
+        assert_eq!(Some("false"), element(xml, "IsTruncated"));
+        assert_eq!(None, element(xml, "Missing"));
+    }
+
+    #[test]
+    fn test_escape() {
+        let s = "a&b <c> \"d\" 'e' ü";
+        assert_eq!(s, unescape(&escape(s)));
+        assert_eq!("A&&#", unescape("&#65;&#x26;&#"));
+    }
+}
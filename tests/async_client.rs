@@ -0,0 +1,96 @@
+//! Tests of the async client against the mock S3 server.
+
+use s3v4::client::{AsyncClient, AsyncMultipartUpload};
+use s3v4::test_util::{MockResponse, MockS3Server};
+use s3v4::{Credentials, Error, ErrorKind, Method, Region, Result};
+
+fn start() -> Result<(MockS3Server, AsyncClient)> {
+    let server = MockS3Server::start(Credentials::new("access", "secret"), Region::UsEast1)?;
+    let client = AsyncClient::new(server.endpoint(), "access", "secret", Region::UsEast1);
+    Ok((server, client))
+}
+
+#[tokio::test]
+async fn test_objects() -> Result<()> {
+    let (server, client) = start()?;
+    let etag = client.put_object("b", "dir/key", b"data".to_vec()).await?;
+    assert_eq!(Some(b"data".to_vec()), server.object("b", "dir/key"));
+    assert_eq!(4, client.content_length("b", "dir/key").await?);
+    let info = client.head_object("b", "dir/key", None).await?;
+    assert_eq!(4, info.content_length);
+    assert_eq!(etag, info.etag);
+    let mut data = Vec::new();
+    assert_eq!(4, client.get_object("b", "dir/key", &mut data).await?);
+    assert_eq!(b"data".to_vec(), data);
+    let mut data = Vec::new();
+    client
+        .get_object_range("b", "dir/key", 1..3, &mut data)
+        .await?;
+    assert_eq!(b"at".to_vec(), data);
+    // the range header is signed like in the blocking client
+    let request = server.requests().pop().ok_or("No request")?;
+    let authorization = request
+        .headers
+        .iter()
+        .find(|(k, _)| k == "authorization")
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default();
+    assert!(authorization.contains(";range;"));
+    server.put_object("b", "other", b"");
+    let objects = client.list_objects("b", "dir/").await?;
+    assert_eq!(1, objects.len());
+    assert_eq!("dir/key", objects[0].key);
+    client.delete_object("b", "dir/key", None).await?;
+    assert_eq!(None, server.object("b", "dir/key"));
+    match client.get_object("b", "dir/key", &mut Vec::new()).await {
+        Err(Error(ErrorKind::HttpStatus(404, _), _)) => {}
+        r => panic!("Expected 404, received {:?}", r.map(|_| ())),
+    }
+    assert!(server
+        .requests()
+        .iter()
+        .all(|request| request.access.is_some()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_multipart_upload() -> Result<()> {
+    let (server, client) = start()?;
+    server.respond(
+        Method::Post,
+        "/b/key",
+        MockResponse::new(
+            200,
+            "<InitiateMultipartUploadResult><UploadId>id</UploadId>\
+             </InitiateMultipartUploadResult>",
+        ),
+    );
+    server.respond(
+        Method::Put,
+        "/b/key",
+        MockResponse::new(200, "").with_header("etag", "\"a\""),
+    );
+    let mut upload = AsyncMultipartUpload::start(&client, "b", "key", 4, 5 << 20).await?;
+    assert_eq!("id", upload.state().upload_id);
+    upload.upload(&mut std::io::Cursor::new(b"data")).await?;
+    assert_eq!(1, upload.state().parts.len());
+    server.respond(
+        Method::Post,
+        "/b/key",
+        MockResponse::new(
+            200,
+            "<CompleteMultipartUploadResult><ETag>&quot;e-1&quot;</ETag>\
+             </CompleteMultipartUploadResult>",
+        ),
+    );
+    assert_eq!("\"e-1\"", upload.complete().await?);
+    let requests = server.requests();
+    assert_eq!(3, requests.len());
+    assert_eq!(Some("uploads"), requests[0].url.query());
+    assert_eq!(Some("partNumber=1&uploadId=id"), requests[1].url.query());
+    assert_eq!(b"data".to_vec(), requests[1].body);
+    assert_eq!(Some("uploadId=id"), requests[2].url.query());
+    assert!(String::from_utf8_lossy(&requests[2].body).contains("<PartNumber>1</PartNumber>"));
+    assert!(requests.iter().all(|request| request.access.is_some()));
+    Ok(())
+}
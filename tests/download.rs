@@ -0,0 +1,146 @@
+//! Tests of parallel ranged downloads against the mock S3 server.
+
+use s3v4::client::{Client, DownloadOptions, TransferEvent};
+use s3v4::test_util::{MockResponse, MockS3Server};
+use s3v4::{Credentials, Error, ErrorKind, Method, Region, Result};
+use std::path::PathBuf;
+
+fn start(data: &[u8]) -> Result<(MockS3Server, Client)> {
+    let server = MockS3Server::start(Credentials::new("access", "secret"), Region::UsEast1)?;
+    server.put_object("b", "key", data);
+    let client = Client::new(server.endpoint(), "access", "secret", Region::UsEast1);
+    Ok((server, client))
+}
+
+/// Return a path in the temporary directory, unique to the test `name`.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("s3v4-{}-{}", name, std::process::id()))
+}
+
+fn data() -> Vec<u8> {
+    (0..100_000_u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn options() -> DownloadOptions {
+    DownloadOptions {
+        chunk_size: 7_000,
+        concurrency: 4,
+        ..Default::default()
+    }
+}
+
+/// Return the number of `GET` requests received by `server`.
+fn gets(server: &MockS3Server) -> usize {
+    server
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::Get)
+        .count()
+}
+
+#[test]
+fn test_download_parallel() -> Result<()> {
+    let data = data();
+    let (server, client) = start(&data)?;
+    let path = temp_path("download");
+    let mut events = Vec::new();
+    let size = client
+        .download_parallel_with_progress("b", "key", &path, &options(), |e| events.push(e))?;
+    let downloaded = std::fs::read(&path).map_err(|e| e.to_string())?;
+    std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    assert_eq!(data.len() as u64, size);
+    assert!(data == downloaded);
+    // 15 chunks, the last one of 2000 bytes
+    assert_eq!(15, gets(&server));
+    assert!(server
+        .requests()
+        .iter()
+        .filter(|r| r.method == Method::Get)
+        .all(|r| r.headers.iter().any(|(k, _)| k == "if-match")));
+    let received = events
+        .iter()
+        .map(|e| match e {
+            TransferEvent::BytesTransferred { bytes } => *bytes,
+            _ => 0,
+        })
+        .sum::<u64>();
+    assert_eq!(size, received);
+    assert_eq!(Some(&TransferEvent::Completed), events.last());
+    Ok(())
+}
+
+#[test]
+fn test_download_retry() -> Result<()> {
+    let data = data();
+    let (server, client) = start(&data)?;
+    let path = temp_path("download-retry");
+    let error = MockResponse::error(500, "InternalError", "We encountered an internal error.");
+    server.respond_once(Method::Get, "/b/key", error.clone());
+    // a truncated response, whose bytes must not be reported
+    server.respond_once(
+        Method::Get,
+        "/b/key",
+        MockResponse::new(206, "abc").with_header("content-range", "bytes 0-6999/100000"),
+    );
+    let mut events = Vec::new();
+    client.download_parallel_with_progress("b", "key", &path, &options(), |e| events.push(e))?;
+    let downloaded = std::fs::read(&path).map_err(|e| e.to_string())?;
+    assert!(data == downloaded);
+    assert_eq!(17, gets(&server));
+    let retries = events
+        .iter()
+        .filter(|e| matches!(e, TransferEvent::Retry { .. }))
+        .count();
+    assert_eq!(2, retries);
+    let received = events
+        .iter()
+        .map(|e| match e {
+            TransferEvent::BytesTransferred { bytes } => *bytes,
+            _ => 0,
+        })
+        .sum::<u64>();
+    assert_eq!(data.len() as u64, received);
+
+    // no more retries
+    server.respond_once(Method::Get, "/b/key", error);
+    let options = DownloadOptions {
+        retries: 0,
+        ..options()
+    };
+    let result = client.download_parallel("b", "key", &path, &options);
+    std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    match result {
+        Err(Error(ErrorKind::HttpStatus(500, _), _)) => {}
+        r => panic!("Expected 500, received {:?}", r),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_download_replaced() -> Result<()> {
+    let data = data();
+    let (server, client) = start(&data)?;
+    let path = temp_path("download-replaced");
+    // the object was replaced after the HEAD request
+    server.respond(
+        Method::Head,
+        "/b/key",
+        MockResponse::new(200, "")
+            .with_header("etag", "\"previous\"")
+            .with_header("content-length", &data.len().to_string()),
+    );
+    let mut events = Vec::new();
+    let result =
+        client.download_parallel_with_progress("b", "key", &path, &options(), |e| events.push(e));
+    std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    match result {
+        Err(Error(ErrorKind::HttpStatus(412, _), _)) => {}
+        r => panic!("Expected 412, received {:?}", r),
+    }
+    // precondition failures are not retried
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, TransferEvent::Retry { .. })));
+    assert!(!events.contains(&TransferEvent::Completed));
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Tests of resumed multipart uploads against the mock S3 server.
+
+use s3v4::client::{Client, MultipartUpload, MultipartUploadState, TransferEvent, MIN_PART_SIZE};
+use s3v4::test_util::{MockResponse, MockS3Server};
+use s3v4::{Credentials, Method, Region, Result};
+use std::cell::RefCell;
+use std::io::Cursor;
+
+#[test]
+fn test_resume() -> Result<()> {
+    let server = MockS3Server::start(Credentials::new("access", "secret"), Region::UsEast1)?;
+    let client = Client::new(server.endpoint(), "access", "secret", Region::UsEast1);
+    let size = 2 * MIN_PART_SIZE + 1;
+    let data = (0..size).map(|i| i as u8).collect::<Vec<_>>();
+    server.respond(
+        Method::Post,
+        "/b/key",
+        MockResponse::new(
+            200,
+            "<InitiateMultipartUploadResult><UploadId>id</UploadId>\
+             </InitiateMultipartUploadResult>",
+        ),
+    );
+    server.respond(
+        Method::Put,
+        "/b/key",
+        MockResponse::new(200, "").with_header("etag", "\"p\""),
+    );
+    let mut upload = MultipartUpload::start(&client, "b", "key", size, MIN_PART_SIZE)?;
+    assert!(upload.upload_next_part(&mut Cursor::new(&data))?);
+    let saved = serde_json::to_string(upload.state()).map_err(|e| e.to_string())?;
+    drop(upload);
+
+    let state: MultipartUploadState = serde_json::from_str(&saved).map_err(|e| e.to_string())?;
+    assert_eq!(1, state.parts.len());
+    // part 2 was not fully uploaded and part 4 does not belong to the object
+    server.respond(
+        Method::Get,
+        "/b/key",
+        MockResponse::new(
+            200,
+            &format!(
+                "<ListPartsResult><IsTruncated>false</IsTruncated>\
+                 <Part><PartNumber>1</PartNumber><ETag>&quot;a&quot;</ETag><Size>{}</Size></Part>\
+                 <Part><PartNumber>2</PartNumber><ETag>&quot;b&quot;</ETag><Size>1</Size></Part>\
+                 <Part><PartNumber>4</PartNumber><ETag>&quot;c&quot;</ETag><Size>1</Size></Part>\
+                 </ListPartsResult>",
+                MIN_PART_SIZE
+            ),
+        ),
+    );
+    let events = RefCell::new(Vec::new());
+    let mut upload = MultipartUpload::resume(&client, state)?
+        .with_progress(|event| events.borrow_mut().push(event));
+    assert_eq!(1, upload.state().parts.len());
+    assert_eq!("\"a\"", upload.state().parts[0].etag);
+    let uploaded = server.requests().len();
+    upload.upload(&mut Cursor::new(&data))?;
+    let parts = upload
+        .state()
+        .parts
+        .iter()
+        .map(|p| p.part_number)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![1, 2, 3], parts);
+    drop(upload);
+    assert_eq!(
+        Some(&TransferEvent::Started {
+            total: Some(size - MIN_PART_SIZE)
+        }),
+        events.borrow().first()
+    );
+
+    let requests = server.requests();
+    let requests = &requests[uploaded..];
+    assert_eq!(2, requests.len());
+    assert!(requests.iter().all(|r| r.method == Method::Put));
+    assert_eq!(Some("partNumber=2&uploadId=id"), requests[0].url.query());
+    assert_eq!(
+        &data[MIN_PART_SIZE as usize..2 * MIN_PART_SIZE as usize],
+        &requests[0].body[..]
+    );
+    assert_eq!(Some("partNumber=3&uploadId=id"), requests[1].url.query());
+    assert_eq!(&data[2 * MIN_PART_SIZE as usize..], &requests[1].body[..]);
+    Ok(())
+}
+
+#[test]
+fn test_retry_progress() -> Result<()> {
+    let server = MockS3Server::start(Credentials::new("access", "secret"), Region::UsEast1)?;
+    let client = Client::new(server.endpoint(), "access", "secret", Region::UsEast1);
+    server.respond(
+        Method::Post,
+        "/b/key",
+        MockResponse::new(
+            200,
+            "<InitiateMultipartUploadResult><UploadId>id</UploadId>\
+             </InitiateMultipartUploadResult>",
+        ),
+    );
+    server.respond(
+        Method::Put,
+        "/b/key",
+        MockResponse::new(200, "").with_header("etag", "\"p\""),
+    );
+    server.respond_once(
+        Method::Put,
+        "/b/key",
+        MockResponse::error(503, "SlowDown", "Please reduce your request rate."),
+    );
+    let mut events = Vec::new();
+    let mut upload = MultipartUpload::start(&client, "b", "key", 4, MIN_PART_SIZE)?
+        .with_progress(|event| events.push(event));
+    upload.upload(&mut Cursor::new(b"data"))?;
+    drop(upload);
+    // the bytes sent by the failed attempt are not reported
+    assert!(matches!(
+        &events[..],
+        [
+            TransferEvent::Started { total: Some(4) },
+            TransferEvent::Retry {
+                part_number: 1,
+                attempt: 1,
+                ..
+            },
+            TransferEvent::BytesTransferred { bytes: 4 },
+            TransferEvent::PartCompleted {
+                part_number: 1,
+                size: 4
+            },
+        ]
+    ));
+    Ok(())
+}